@@ -1,11 +1,12 @@
 use anyhow::{Context, Result};
+use atrium_xrpc_client::reqwest::ReqwestClientBuilder;
 use bsky_sdk::BskyAgent;
 use rmcp::ServiceExt;
 use std::{env, io};
 use tokio::io::{stdin, stdout};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
-use bsky_rmcp::BskyService;
+use bsky_rmcp::{BskyService, Config, LimitedClient};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -15,7 +16,16 @@ async fn main() -> Result<()> {
         .with_ansi(false)
         .init();
 
-    let agent = BskyAgent::builder().build().await?;
+    let config = Config::from_env()?;
+    let http = config.http_client()?;
+    let client = LimitedClient::new(
+        ReqwestClientBuilder::new("https://bsky.social")
+            .client(http.clone())
+            .build(),
+        config.max_concurrent_requests,
+    );
+    let rate_limit = client.rate_limit();
+    let agent = BskyAgent::builder().client(client).build().await?;
     let identifier = env::var("BLUESKY_IDENTIFIER")
         .context("failed to get environment variable BLUESKY_IDENTIFIER")?;
     let password = env::var("BLUESKY_APP_PASSWORD")
@@ -27,13 +37,15 @@ async fn main() -> Result<()> {
         session.did.as_str()
     );
 
+    let service = BskyService::new(agent, config, http, rate_limit);
+    service.spawn_session_refresher();
+    #[cfg(feature = "metrics")]
+    service.spawn_metrics_logger();
+
     let transport = (stdin(), stdout());
-    let service = BskyService::new(agent)
-        .serve(transport)
-        .await
-        .inspect_err(|e| {
-            tracing::error!("serving error: {:?}", e);
-        })?;
+    let service = service.serve(transport).await.inspect_err(|e| {
+        tracing::error!("serving error: {:?}", e);
+    })?;
     service.waiting().await?;
     Ok(())
 }
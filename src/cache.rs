@@ -0,0 +1,93 @@
+use rmcp::model::CallToolResult;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Names of the tools that only read data, whose results can be cached.
+const CACHEABLE_TOOLS: &[&str] = &[
+    "get_profile",
+    "get_profiles",
+    "resolve_did",
+    "verify_handle",
+    "get_author_feed",
+    "get_timeline",
+    "get_my_feeds",
+    "get_feed_generators",
+    "get_feed",
+    "get_list_feed",
+    "get_post_thread",
+    "search_posts",
+    "get_list_blocks",
+    "get_list_mutes",
+    "get_actor_starter_packs",
+    "get_followers",
+    "get_follows",
+    "get_mutuals",
+    "get_blob",
+    "get_latest_commit",
+];
+
+/// Names of the tools that write data or switch the account, which invalidate cached results.
+const WRITE_TOOLS: &[&str] = &[
+    "switch_account",
+    "update_handle",
+    "set_feed_view_pref",
+    "send_interactions",
+    "follow_all",
+    "upload_blob",
+    "put_record",
+    "delete_record",
+    "create_post",
+    "quote_post",
+    "like",
+    "unlike",
+    "repost",
+    "delete_post",
+    "block",
+    "unblock",
+    "mute",
+    "unmute",
+    "report",
+];
+
+/// Short-lived cache of tool results, keyed by tool name and arguments.
+#[derive(Debug)]
+pub struct ResponseCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, CallToolResult)>>,
+}
+
+impl ResponseCache {
+    /// Creates a cache, which is disabled if `ttl` is zero.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+    /// Returns whether results of the tool can be cached.
+    pub fn is_cacheable(&self, tool: &str) -> bool {
+        !self.ttl.is_zero() && CACHEABLE_TOOLS.contains(&tool)
+    }
+    /// Returns whether calling the tool invalidates the cached results.
+    pub fn is_invalidated_by(&self, tool: &str) -> bool {
+        WRITE_TOOLS.contains(&tool)
+    }
+    pub fn get(&self, key: &str) -> Option<CallToolResult> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (inserted, _)| inserted.elapsed() < self.ttl);
+        entries.get(key).map(|(_, result)| result.clone())
+    }
+    pub fn insert(&self, key: String, result: CallToolResult) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(key, (Instant::now(), result));
+    }
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
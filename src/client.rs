@@ -0,0 +1,81 @@
+use atrium_xrpc::{
+    HttpClient, XrpcClient,
+    http::{Request, Response},
+};
+use atrium_xrpc_client::reqwest::ReqwestClient;
+use bsky_sdk::BskyAgent;
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::Semaphore;
+
+/// Agent whose requests are limited by `LimitedClient`.
+pub type Agent = BskyAgent<LimitedClient>;
+
+/// Rate limit headers of the last response from the server that had them.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit {
+    /// Number of requests remaining in the current window (`ratelimit-remaining`).
+    pub remaining: Option<u64>,
+    /// Time when the window resets, in seconds since the epoch (`ratelimit-reset`).
+    pub reset: Option<u64>,
+}
+
+/// XRPC client that limits the number of concurrent requests to the server, shared by all
+/// tools.
+pub struct LimitedClient<T = ReqwestClient> {
+    inner: T,
+    semaphore: Semaphore,
+    rate_limit: Arc<Mutex<RateLimit>>,
+}
+
+impl<T> LimitedClient<T> {
+    /// Creates a client that sends at most `max_concurrent` requests at a time.
+    pub fn new(inner: T, max_concurrent: usize) -> Self {
+        Self {
+            inner,
+            semaphore: Semaphore::new(max_concurrent.max(1)),
+            rate_limit: Arc::default(),
+        }
+    }
+    /// Returns the rate limit headers of the last response, updated by the client.
+    pub fn rate_limit(&self) -> Arc<Mutex<RateLimit>> {
+        self.rate_limit.clone()
+    }
+}
+
+impl<T> HttpClient for LimitedClient<T>
+where
+    T: HttpClient + Send + Sync,
+{
+    async fn send_http(
+        &self,
+        request: Request<Vec<u8>>,
+    ) -> Result<Response<Vec<u8>>, Box<dyn Error + Send + Sync + 'static>> {
+        let _permit = self.semaphore.acquire().await?;
+        let response = self.inner.send_http(request).await?;
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+        };
+        let (remaining, reset) = (header("ratelimit-remaining"), header("ratelimit-reset"));
+        if remaining.is_some() || reset.is_some() {
+            *self.rate_limit.lock().unwrap_or_else(|e| e.into_inner()) =
+                RateLimit { remaining, reset };
+        }
+        Ok(response)
+    }
+}
+
+impl<T> XrpcClient for LimitedClient<T>
+where
+    T: XrpcClient + Send + Sync,
+{
+    fn base_uri(&self) -> String {
+        self.inner.base_uri()
+    }
+}
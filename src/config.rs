@@ -0,0 +1,238 @@
+use crate::{types::ReasonEnum, utils::parse_language};
+use anyhow::{Context, Result, anyhow};
+use bsky_sdk::api::types::string::Language;
+use serde::{Deserialize, de::IntoDeserializer};
+use std::{
+    collections::{HashMap, HashSet},
+    env, error, fmt,
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 8;
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+const DEFAULT_MAX_BLOB_SIZE: usize = 1_000_000;
+const DEFAULT_MAX_THREAD_SIZE: usize = 500_000;
+const DEFAULT_MENTION_THREAD_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_TOOL_TIMEOUT_SECS: u64 = 300;
+const DEFAULT_TOKEN_REFRESH_THRESHOLD_SECS: u64 = 300;
+#[cfg(feature = "metrics")]
+const DEFAULT_METRICS_INTERVAL_SECS: u64 = 60;
+
+/// Server configuration read from environment variables.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Timeout for connecting to the server (`BSKY_CONNECT_TIMEOUT`, in seconds).
+    pub connect_timeout: Duration,
+    /// Timeout for a whole request (`BSKY_REQUEST_TIMEOUT`, in seconds).
+    pub request_timeout: Duration,
+    /// Maximum number of concurrent requests to the server across all tools
+    /// (`BSKY_MAX_CONCURRENT_REQUESTS`).
+    pub max_concurrent_requests: usize,
+    /// Deadline for a whole tool invocation (`BSKY_TOOL_TIMEOUT`, in seconds).
+    pub tool_timeout: Duration,
+    /// `User-Agent` header of requests to the server (`BSKY_USER_AGENT`).
+    pub user_agent: String,
+    /// Time to live of cached results of read tools (`BSKY_CACHE_TTL`, in seconds).
+    /// Caching is disabled if zero.
+    pub cache_ttl: Duration,
+    /// Remaining lifetime of the access token below which the session is refreshed
+    /// (`BSKY_TOKEN_REFRESH_THRESHOLD`, in seconds).
+    pub token_refresh_threshold: Duration,
+    /// Whether JSON results of tools are pretty-printed (`BSKY_PRETTY_JSON`).
+    pub pretty_json: bool,
+    /// Whether the `switch_account` tool is enabled (`BSKY_ENABLE_ACCOUNT_SWITCHING`).
+    pub account_switching: bool,
+    /// Whether the `update_handle` tool is enabled (`BSKY_ENABLE_HANDLE_UPDATE`).
+    pub handle_update: bool,
+    /// Accounts that can be switched to, keyed by alias (`BSKY_ACCOUNTS`).
+    pub accounts: HashMap<String, Account>,
+    /// Maximum size of a blob returned by `get_blob` or uploaded by `upload_blob`
+    /// (`BSKY_MAX_BLOB_SIZE`, in bytes).
+    pub max_blob_size: usize,
+    /// Maximum serialized size of a thread returned by `get_post_thread`
+    /// (`BSKY_MAX_THREAD_SIZE`, in bytes).
+    pub max_thread_size: usize,
+    /// Path of the file where `get_profile` records follower and following counts
+    /// (`BSKY_COUNT_STORE`). Tracking is disabled if unset.
+    pub count_store: Option<PathBuf>,
+    /// Path of the file where feed tools record the most recent posts seen with `mark_new`
+    /// (`BSKY_FEED_MARKER_STORE`). Feed markers are disabled if unset.
+    pub feed_marker_store: Option<PathBuf>,
+    /// Deadline for fetching each thread in `get_unreplied_mentions`
+    /// (`BSKY_MENTION_THREAD_TIMEOUT`, in seconds).
+    pub mention_thread_timeout: Duration,
+    /// Whether `create_post` rejects images without alt text (`BSKY_REQUIRE_ALT_TEXT`).
+    pub require_alt_text: bool,
+    /// Window in which `create_post` rejects the same text as a recent post
+    /// (`BSKY_DUPLICATE_POST_WINDOW`, in seconds). The guard is disabled if zero.
+    pub duplicate_post_window: Duration,
+    /// Language of posts created without explicit `langs` (`BSKY_DEFAULT_LANG`).
+    pub default_lang: Option<Language>,
+    /// Notification reasons used by `list_notifications` when none are given
+    /// (`BSKY_DEFAULT_NOTIFICATION_REASONS`). All reasons are included if empty.
+    pub notification_reasons: HashSet<String>,
+    /// Names of the only tools that can be called, if set (`BSKY_TOOLS_ALLOW`).
+    pub tools_allow: Option<HashSet<String>>,
+    /// Names of tools that cannot be called (`BSKY_TOOLS_DENY`).
+    pub tools_deny: HashSet<String>,
+    /// Interval for logging tool metrics (`BSKY_METRICS_INTERVAL`, in seconds).
+    #[cfg(feature = "metrics")]
+    pub metrics_interval: Duration,
+}
+
+/// Credentials of an account that can be switched to.
+#[derive(Clone)]
+pub struct Account {
+    pub identifier: String,
+    pub password: String,
+}
+
+impl fmt::Debug for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Account")
+            .field("identifier", &self.identifier)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self> {
+        Ok(Config {
+            connect_timeout: Duration::from_secs(
+                parse_env("BSKY_CONNECT_TIMEOUT")?.unwrap_or(DEFAULT_TIMEOUT_SECS),
+            ),
+            request_timeout: Duration::from_secs(
+                parse_env("BSKY_REQUEST_TIMEOUT")?.unwrap_or(DEFAULT_TIMEOUT_SECS),
+            ),
+            max_concurrent_requests: parse_env("BSKY_MAX_CONCURRENT_REQUESTS")?
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_REQUESTS),
+            user_agent: env::var("BSKY_USER_AGENT").unwrap_or_else(|_| DEFAULT_USER_AGENT.into()),
+            tool_timeout: Duration::from_secs(
+                parse_env("BSKY_TOOL_TIMEOUT")?.unwrap_or(DEFAULT_TOOL_TIMEOUT_SECS),
+            ),
+            cache_ttl: Duration::from_secs(parse_env("BSKY_CACHE_TTL")?.unwrap_or_default()),
+            token_refresh_threshold: Duration::from_secs(
+                parse_env("BSKY_TOKEN_REFRESH_THRESHOLD")?
+                    .unwrap_or(DEFAULT_TOKEN_REFRESH_THRESHOLD_SECS),
+            ),
+            pretty_json: parse_env("BSKY_PRETTY_JSON")?.unwrap_or_default(),
+            account_switching: parse_env("BSKY_ENABLE_ACCOUNT_SWITCHING")?.unwrap_or_default(),
+            handle_update: parse_env("BSKY_ENABLE_HANDLE_UPDATE")?.unwrap_or_default(),
+            accounts: match env::var("BSKY_ACCOUNTS") {
+                Ok(value) => parse_accounts(&value)?,
+                Err(_) => HashMap::new(),
+            },
+            max_blob_size: parse_env("BSKY_MAX_BLOB_SIZE")?.unwrap_or(DEFAULT_MAX_BLOB_SIZE),
+            max_thread_size: parse_env("BSKY_MAX_THREAD_SIZE")?.unwrap_or(DEFAULT_MAX_THREAD_SIZE),
+            count_store: env::var_os("BSKY_COUNT_STORE").map(PathBuf::from),
+            feed_marker_store: env::var_os("BSKY_FEED_MARKER_STORE").map(PathBuf::from),
+            mention_thread_timeout: Duration::from_secs(
+                parse_env("BSKY_MENTION_THREAD_TIMEOUT")?
+                    .unwrap_or(DEFAULT_MENTION_THREAD_TIMEOUT_SECS),
+            ),
+            require_alt_text: parse_env("BSKY_REQUIRE_ALT_TEXT")?.unwrap_or_default(),
+            duplicate_post_window: Duration::from_secs(
+                parse_env("BSKY_DUPLICATE_POST_WINDOW")?.unwrap_or_default(),
+            ),
+            default_lang: env::var("BSKY_DEFAULT_LANG")
+                .ok()
+                .map(|lang| parse_language(&lang).context("invalid value for BSKY_DEFAULT_LANG"))
+                .transpose()?,
+            notification_reasons: env::var("BSKY_DEFAULT_NOTIFICATION_REASONS")
+                .ok()
+                .map(|value| parse_reasons(&value))
+                .transpose()?
+                .unwrap_or_default(),
+            tools_allow: env::var("BSKY_TOOLS_ALLOW")
+                .ok()
+                .map(|value| parse_list(&value)),
+            tools_deny: env::var("BSKY_TOOLS_DENY")
+                .map(|value| parse_list(&value))
+                .unwrap_or_default(),
+            #[cfg(feature = "metrics")]
+            metrics_interval: Duration::from_secs(
+                parse_env("BSKY_METRICS_INTERVAL")?.unwrap_or(DEFAULT_METRICS_INTERVAL_SECS),
+            ),
+        })
+    }
+    /// Builds an HTTP client with the configured timeouts and `User-Agent`.
+    pub fn http_client(&self) -> reqwest::Result<reqwest::Client> {
+        reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.request_timeout)
+            .user_agent(&self.user_agent)
+            .build()
+    }
+    /// Returns whether the tool is enabled by the allowlist and denylist.
+    pub fn is_tool_enabled(&self, name: &str) -> bool {
+        self.tools_allow
+            .as_ref()
+            .is_none_or(|allow| allow.contains(name))
+            && !self.tools_deny.contains(name)
+    }
+}
+
+/// Parses a comma-separated list of names.
+fn parse_list(value: &str) -> HashSet<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Parses a comma-separated list of notification reasons, rejecting unknown ones.
+fn parse_reasons(value: &str) -> Result<HashSet<String>> {
+    let reasons = parse_list(value);
+    for reason in &reasons {
+        ReasonEnum::deserialize(reason.as_str().into_deserializer()).map_err(
+            |e: serde::de::value::Error| {
+                anyhow!("invalid value for BSKY_DEFAULT_NOTIFICATION_REASONS: {e}")
+            },
+        )?;
+    }
+    Ok(reasons)
+}
+
+/// Parses accounts in the form of `alias=identifier:password,...`.
+fn parse_accounts(value: &str) -> Result<HashMap<String, Account>> {
+    value
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let (alias, credentials) = entry.trim().split_once('=').ok_or(anyhow!(
+                "invalid account entry in BSKY_ACCOUNTS: missing alias"
+            ))?;
+            // The identifier may be a DID which contains colons, so split at the last one
+            let (identifier, password) = credentials
+                .rsplit_once(':')
+                .ok_or(anyhow!("invalid account entry in BSKY_ACCOUNTS: {alias}"))?;
+            Ok((
+                alias.into(),
+                Account {
+                    identifier: identifier.into(),
+                    password: password.into(),
+                },
+            ))
+        })
+        .collect()
+}
+
+fn parse_env<T>(key: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: error::Error + Send + Sync + 'static,
+{
+    match env::var(key) {
+        Ok(value) => Ok(Some(value.parse().with_context(|| {
+            format!("invalid value for environment variable {key}")
+        })?)),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("failed to get environment variable {key}")),
+    }
+}
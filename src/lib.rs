@@ -1,5 +1,13 @@
+mod cache;
+mod client;
+mod config;
+#[cfg(feature = "metrics")]
+mod metrics;
 mod service;
+mod store;
 pub mod types;
 mod utils;
 
+pub use self::client::{Agent, LimitedClient, RateLimit};
+pub use self::config::Config;
 pub use self::service::BskyService;
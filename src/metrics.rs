@@ -0,0 +1,34 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// Counters of tool invocations and errors, keyed by tool name.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    tools: Mutex<BTreeMap<String, Counter>>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Counter {
+    calls: u64,
+    errors: u64,
+}
+
+impl Metrics {
+    pub fn record(&self, tool: &str, is_error: bool) {
+        let mut tools = self.tools.lock().unwrap_or_else(|e| e.into_inner());
+        let counter = tools.entry(tool.into()).or_default();
+        counter.calls += 1;
+        if is_error {
+            counter.errors += 1;
+        }
+    }
+    pub fn log(&self) {
+        let tools = self.tools.lock().unwrap_or_else(|e| e.into_inner());
+        for (tool, counter) in tools.iter() {
+            tracing::info!(
+                "tool {tool}: {} calls, {} errors",
+                counter.calls,
+                counter.errors
+            );
+        }
+    }
+}
@@ -1,53 +1,597 @@
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
 use crate::{
+    cache::ResponseCache,
+    client::{Agent, RateLimit},
+    config::Config,
+    store::{CountObservation, FeedMarker, FileStore},
     types::{
-        CreatePostParams, DEFAULT_DEPTH, DEFAULT_LIMIT, DEFAULT_PARENT_HEIGHT, GetAuthorFeedParams,
-        GetPostThreadParams, ListNotificationsParams, ReasonEnum, SearchPostsParams,
+        CreatePostParams, DEFAULT_DEPTH, DEFAULT_EMBED_DEPTH, DEFAULT_LIMIT, DEFAULT_PARENT_HEIGHT,
+        DeletePostParams, DeleteRecordParams, FollowAllParams, GetActorStarterPacksParams,
+        GetAuthorFeedParams, GetFeedGeneratorsParams, GetFeedParams, GetFollowersParams,
+        GetFollowsParams, GetListBlocksParams, GetListFeedParams, GetListMutesParams,
+        GetPostThreadParams, GetProfilesParams, GetServiceAuthParams, GetTimelineParams,
+        LikeParams, ListNotificationsParams, MAX_AUTHOR_FEED_PAGES, MAX_DEPTH, MAX_EMBED_DEPTH,
+        MAX_GRAPH_PAGES, MAX_IMAGES, MAX_PARENT_HEIGHT, MAX_PROFILES, PutRecordParams,
+        QuotePostParams, ReasonEnum, ReportParams, RepostParams, SearchPostsParams,
+        SendInteractionsParams, SetFeedViewPrefParams, ThreadFormatEnum, UnlikeParams,
+        UploadBlobParams,
+    },
+    utils::{
+        LinkCard, RATE_LIMIT_ERROR_CODE, TIMEOUT_ERROR_CODE, annotate_replied_by,
+        check_mentions_resolved, check_reply_uri, clamp_param, convert_datetime,
+        count_unavailable_posts, detect_image_mime_type, fetch_bytes, fetch_link_card, fill_prompt,
+        get_post, hydrate_quotes, invites_disabled, is_record_not_found, jwt_expiry, limit_replies,
+        limit_to_param, map_internal_err, map_invalid_params, map_xrpc_err, new_replies_since,
+        normalize_post_ref, parse_at_uri, parse_datetime, parse_duration, parse_language,
+        post_web_url, process_reposts, rank_posts, render_thread_transcript, resolve_did_document,
+        select_reply_root, shorten_links, strong_ref_from_record, strong_ref_from_uri,
+        surface_videos, truncate_thread, xrpc_error,
     },
-    utils::{convert_datetime, get_post},
 };
+use base64::{Engine, prelude::BASE64_STANDARD};
 use bsky_sdk::{
-    BskyAgent,
     api::{
         app::bsky,
         com::atproto,
-        types::{LimitedU16, TryFromUnknown, Union, string::Datetime},
+        types::{
+            BlobRef, LimitedU16, TryFromUnknown, Union, Unknown,
+            string::{AtIdentifier, Cid, Datetime, Did, Handle},
+        },
     },
     rich_text::RichText,
 };
+use chrono::{TimeDelta, Utc};
+use futures::{StreamExt, stream};
 use rmcp::{
     Error, RoleServer, ServerHandler,
+    handler::server::tool::ToolCallContext,
     model::{
-        CallToolResult, Content, GetPromptRequestParam, GetPromptResult, ListPromptsResult,
-        PaginatedRequestParam, Prompt, PromptMessage, PromptMessageRole, ServerCapabilities,
-        ServerInfo,
+        CallToolRequestParam, CallToolResult, Content, GetPromptRequestParam, GetPromptResult,
+        ListPromptsResult, ListToolsResult, PaginatedRequestParam, Prompt, PromptArgument,
+        PromptMessage, PromptMessageRole, ServerCapabilities, ServerInfo,
     },
     schemars,
-    serde_json::Value,
+    serde_json::{self, Value, json},
     service::RequestContext,
     tool,
 };
-use std::collections::HashSet;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+/// Maximum number of parent posts fetched concurrently for `search_posts`.
+const PARENT_CONTEXT_CONCURRENCY: usize = 4;
+/// Interval for checking the expiry of the access token.
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Maximum number of recent post texts remembered by the duplicate post guard.
+const RECENT_POST_TEXTS: usize = 20;
+/// Maximum number of characters of a parent post text in its summary.
+const PARENT_CONTEXT_MAX_CHARS: usize = 100;
+/// Depth of the threads fetched by `get_unreplied_mentions`, deep enough to see the replies to
+/// the user's own reply.
+const MENTION_THREAD_DEPTH: u16 = 10;
 
 #[derive(Clone)]
 pub struct BskyService {
-    agent: BskyAgent,
+    agent: Agent,
+    /// Rate limit headers of the last response, shared with the agent's client.
+    rate_limit: Arc<Mutex<RateLimit>>,
+    /// HTTP client for requests other than XRPC, such as DID documents and link cards.
+    http: reqwest::Client,
+    config: Arc<Config>,
+    cache: Arc<ResponseCache>,
+    /// Store of observed follower and following counts, if tracking is enabled.
+    count_store: Option<Arc<FileStore<CountObservation>>>,
+    /// Store of the most recent posts seen in feeds, if feed markers are enabled.
+    feed_markers: Option<Arc<FileStore<FeedMarker>>>,
+    /// Texts of the posts recently created by `create_post`, with their creation times.
+    recent_posts: Arc<Mutex<VecDeque<(Instant, String)>>>,
+    /// DID and handle of the current account, cached from the session.
+    identity: Arc<RwLock<Option<(Did, Handle)>>>,
+    #[cfg(feature = "metrics")]
+    metrics: Arc<Metrics>,
 }
 
 impl BskyService {
-    pub fn new(agent: BskyAgent) -> Self {
-        BskyService { agent }
+    pub fn new(
+        agent: Agent,
+        config: Config,
+        http: reqwest::Client,
+        rate_limit: Arc<Mutex<RateLimit>>,
+    ) -> Self {
+        BskyService {
+            agent,
+            rate_limit,
+            http,
+            cache: Arc::new(ResponseCache::new(config.cache_ttl)),
+            count_store: config.count_store.clone().and_then(|path| {
+                FileStore::open(path)
+                    .inspect_err(|e| tracing::warn!("count tracking is disabled: {e:#}"))
+                    .ok()
+                    .map(Arc::new)
+            }),
+            feed_markers: config.feed_marker_store.clone().and_then(|path| {
+                FileStore::open(path)
+                    .inspect_err(|e| tracing::warn!("feed markers are disabled: {e:#}"))
+                    .ok()
+                    .map(Arc::new)
+            }),
+            recent_posts: Arc::default(),
+            identity: Arc::default(),
+            config: Arc::new(config),
+            #[cfg(feature = "metrics")]
+            metrics: Arc::default(),
+        }
+    }
+    /// Spawns a task that periodically logs the tool metrics.
+    #[cfg(feature = "metrics")]
+    pub fn spawn_metrics_logger(&self) {
+        let metrics = self.metrics.clone();
+        let mut interval = tokio::time::interval(self.config.metrics_interval);
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                metrics.log();
+            }
+        });
+    }
+    /// Spawns a task that refreshes the session before its access token expires, so that
+    /// tool calls after a long idle time do not fail.
+    pub fn spawn_session_refresher(&self) {
+        let agent = self.agent.clone();
+        let threshold = self.config.token_refresh_threshold;
+        let mut interval = tokio::time::interval(SESSION_CHECK_INTERVAL);
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                let Some(session) = agent.get_session().await else {
+                    continue;
+                };
+                let Some(expiry) = jwt_expiry(&session.access_jwt) else {
+                    continue;
+                };
+                if expiry - Utc::now().timestamp() > threshold.as_secs() as i64 {
+                    continue;
+                }
+                let output = match agent.api.com.atproto.server.refresh_session().await {
+                    Ok(output) => output,
+                    Err(e) => {
+                        tracing::warn!("failed to refresh session: {e}");
+                        continue;
+                    }
+                };
+                let mut data = session.data;
+                data.access_jwt = output.data.access_jwt;
+                data.refresh_jwt = output.data.refresh_jwt;
+                data.handle = output.data.handle;
+                let did = data.did.clone();
+                match agent.resume_session(data.into()).await {
+                    Ok(()) => tracing::info!("refreshed session of {}", did.as_str()),
+                    Err(e) => tracing::warn!("failed to resume refreshed session: {e}"),
+                }
+            }
+        });
+    }
+    /// Adds a short summary of the parent post to each reply in an array of post views,
+    /// fetching the parents concurrently.
+    async fn add_parent_context(&self, posts: &mut Value) {
+        let Value::Array(posts) = posts else {
+            return;
+        };
+        let uris = posts
+            .iter()
+            .filter_map(|post| post.pointer("/record/reply/parent/uri")?.as_str())
+            .map(String::from)
+            .collect::<HashSet<_>>();
+        let parents = stream::iter(uris)
+            .map(|uri| async move {
+                let result = self
+                    .agent
+                    .api
+                    .app
+                    .bsky
+                    .feed
+                    .get_posts(
+                        bsky::feed::get_posts::ParametersData {
+                            uris: vec![uri.clone()],
+                        }
+                        .into(),
+                    )
+                    .await;
+                (uri, result)
+            })
+            .buffer_unordered(PARENT_CONTEXT_CONCURRENCY)
+            .filter_map(|(uri, result)| async move {
+                let parent = result
+                    .inspect_err(|e| tracing::warn!("failed to get parent post {uri}: {e}"))
+                    .ok()?
+                    .data
+                    .posts
+                    .pop()?;
+                let record = serde_json::to_value(&parent.record).unwrap_or_default();
+                let text = record["text"].as_str().unwrap_or_default();
+                Some((
+                    uri,
+                    json!({
+                        "uri": parent.uri,
+                        "author": parent.author.handle,
+                        "text": text.chars().take(PARENT_CONTEXT_MAX_CHARS).collect::<String>(),
+                    }),
+                ))
+            })
+            .collect::<HashMap<_, _>>()
+            .await;
+        for post in posts.iter_mut() {
+            let parent = post
+                .pointer("/record/reply/parent/uri")
+                .and_then(Value::as_str)
+                .and_then(|uri| parents.get(uri))
+                .cloned();
+            if let (Some(parent), Value::Object(map)) = (parent, post) {
+                map.insert("parentContext".into(), parent);
+            }
+        }
+    }
+    /// Returns the DID and handle of the current account, reading the session only once.
+    async fn identity(&self) -> Result<(Did, Handle), Error> {
+        // Release the lock before awaiting, so that the future stays `Send`
+        let cached = self
+            .identity
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        if let Some(identity) = cached {
+            return Ok(identity);
+        }
+        let session = self
+            .agent
+            .get_session()
+            .await
+            .ok_or(Error::internal_error("failed to get session", None))?;
+        let identity = (session.data.did, session.data.handle);
+        *self.identity.write().unwrap_or_else(|e| e.into_inner()) = Some(identity.clone());
+        Ok(identity)
+    }
+    /// Returns the DID of the current account.
+    async fn did(&self) -> Result<Did, Error> {
+        Ok(self.identity().await?.0)
+    }
+    /// Converts a reference given as an AT URI or a bsky.app URL to an AT URI.
+    async fn normalize_ref(&self, reference: &str) -> Result<String, Error> {
+        normalize_post_ref(&self.agent, reference)
+            .await
+            .map_err(map_invalid_params("failed to normalize post reference"))
+    }
+    /// Fetches the followers or the follows of an actor, up to `MAX_GRAPH_PAGES` pages.
+    /// Returns whether more accounts remain unfetched.
+    async fn graph_accounts(
+        &self,
+        actor: &AtIdentifier,
+        followers: bool,
+    ) -> Result<(Vec<bsky::actor::defs::ProfileView>, bool), Error> {
+        let limit = limit_to_param(Some(100));
+        let mut accounts = Vec::new();
+        let mut cursor = None;
+        for _ in 0..MAX_GRAPH_PAGES {
+            let (page, next) = if followers {
+                let output = self
+                    .agent
+                    .api
+                    .app
+                    .bsky
+                    .graph
+                    .get_followers(
+                        bsky::graph::get_followers::ParametersData {
+                            actor: actor.clone(),
+                            cursor: cursor.take(),
+                            limit,
+                        }
+                        .into(),
+                    )
+                    .await
+                    .map_err(map_xrpc_err("failed to get followers"))?;
+                (output.data.followers, output.data.cursor)
+            } else {
+                let output = self
+                    .agent
+                    .api
+                    .app
+                    .bsky
+                    .graph
+                    .get_follows(
+                        bsky::graph::get_follows::ParametersData {
+                            actor: actor.clone(),
+                            cursor: cursor.take(),
+                            limit,
+                        }
+                        .into(),
+                    )
+                    .await
+                    .map_err(map_xrpc_err("failed to get follows"))?;
+                (output.data.follows, output.data.cursor)
+            };
+            accounts.extend(page);
+            match next {
+                Some(next) => cursor = Some(next),
+                None => return Ok((accounts, false)),
+            }
+        }
+        Ok((accounts, true))
+    }
+    /// Serializes a value into a JSON content, which is pretty-printed if configured.
+    fn json_content(&self, value: impl Serialize) -> Result<Content, Error> {
+        if !self.config.pretty_json {
+            return Content::json(value);
+        }
+        serde_json::to_string_pretty(&value)
+            .map(Content::text)
+            .map_err(map_internal_err("failed to serialize json"))
+    }
+    /// Marks the items of a feed newer than the marker of the previous check with `isNew`,
+    /// and records the newest item as the marker for the next check. Returns a note about
+    /// the result for the user.
+    async fn mark_new_items(&self, feed: &str, value: &mut Value) -> Result<String, Error> {
+        let Some(store) = &self.feed_markers else {
+            return Ok(
+                "new posts cannot be marked because BSKY_FEED_MARKER_STORE is not set.".into(),
+            );
+        };
+        // Markers are kept per account, since feeds such as the timeline differ between them
+        let key = format!("{}:{feed}", self.did().await?.as_str());
+        let previous = store.get(&key);
+        let since = previous
+            .as_ref()
+            .and_then(|marker| marker.indexed_at.parse::<Datetime>().ok());
+        // Reposts are new when they were reposted, regardless of when the post was created
+        let indexed_at = |item: &Value| {
+            item.pointer("/reason/indexedAt")
+                .or_else(|| item.pointer("/post/indexedAt"))
+                .and_then(Value::as_str)
+                .and_then(|s| s.parse::<Datetime>().ok())
+        };
+        let mut new_count = 0;
+        let mut newest = None::<(Datetime, String)>;
+        for item in value["feed"].as_array_mut().into_iter().flatten() {
+            let Some(at) = indexed_at(item) else {
+                continue;
+            };
+            let is_new = since
+                .as_ref()
+                .is_none_or(|since| at.as_ref() > since.as_ref());
+            if is_new {
+                new_count += 1;
+                if newest
+                    .as_ref()
+                    .is_none_or(|(newest, _)| at.as_ref() > newest.as_ref())
+                {
+                    newest = Some((at, item["post"]["uri"].as_str().unwrap_or_default().into()));
+                }
+            }
+            item["isNew"] = Value::Bool(is_new);
+        }
+        value["newCount"] = json!(new_count);
+        if let Some((at, uri)) = newest {
+            let marker = FeedMarker {
+                uri,
+                indexed_at: at.as_str().into(),
+            };
+            if let Err(e) = store.record(&key, marker) {
+                tracing::warn!("failed to record feed marker: {e:#}");
+            }
+        }
+        Ok(match previous {
+            Some(previous) => format!(
+                "{new_count} post(s) are new since the last check, whose newest post was indexed at {}.",
+                previous.indexed_at
+            ),
+            None => format!(
+                "this is the first check of the feed, so all {new_count} post(s) are marked as new."
+            ),
+        })
+    }
+    /// Parses a handle or DID of an actor, resolving `me` or `self` to the current account.
+    async fn parse_actor(&self, actor: &str) -> Result<AtIdentifier, Error> {
+        if matches!(actor, "me" | "self") {
+            return Ok(AtIdentifier::Did(self.did().await?));
+        }
+        actor
+            .parse()
+            .map_err(map_invalid_params("failed to parse actor"))
+    }
+    /// Fetches the profile of an actor to be moderated (e.g. blocked), which must not be the
+    /// current account.
+    async fn moderation_target(
+        &self,
+        actor: &str,
+    ) -> Result<bsky::actor::defs::ProfileViewDetailed, Error> {
+        let actor = self.parse_actor(actor).await?;
+        let profile = self
+            .agent
+            .api
+            .app
+            .bsky
+            .actor
+            .get_profile(bsky::actor::get_profile::ParametersData { actor }.into())
+            .await
+            .map_err(map_xrpc_err("failed to get profile"))?;
+        if profile.did == self.did().await? {
+            return Err(Error::invalid_params(
+                "the current account cannot be the target",
+                None,
+            ));
+        }
+        Ok(profile)
+    }
+    /// Summarizes the actor affected by a moderation action, so that the user can confirm the
+    /// right account was affected.
+    fn moderated_actor(profile: &bsky::actor::defs::ProfileViewDetailed) -> Value {
+        json!({
+            "did": profile.did,
+            "handle": profile.handle,
+            "displayName": profile.display_name,
+        })
+    }
+    /// Fetches a post to be liked or reposted (the `action`), rejecting references to other
+    /// records. Returns a strong reference to the post and the current account's view of it.
+    async fn post_subject(
+        &self,
+        reference: &str,
+        cid: Option<&str>,
+        action: &str,
+    ) -> Result<
+        (
+            atproto::repo::strong_ref::Main,
+            Option<bsky::feed::defs::ViewerState>,
+        ),
+        Error,
+    > {
+        let uri = self.normalize_ref(reference).await?;
+        let (_, collection, _) =
+            parse_at_uri(&uri).map_err(map_invalid_params("failed to parse uri"))?;
+        if collection.as_str() != "app.bsky.feed.post" {
+            return Err(Error::invalid_params(
+                format!(
+                    "only posts can be {action}, but {uri} is a {} record",
+                    collection.as_str()
+                ),
+                None,
+            ));
+        }
+        let cid = cid
+            .map(str::parse::<Cid>)
+            .transpose()
+            .map_err(map_invalid_params("failed to parse cid"))?;
+        // The post view tells whether the current account already liked or reposted it
+        let post = self
+            .agent
+            .api
+            .app
+            .bsky
+            .feed
+            .get_posts(
+                bsky::feed::get_posts::ParametersData {
+                    uris: vec![uri.clone()],
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to get post"))?
+            .data
+            .posts
+            .pop()
+            .ok_or(Error::invalid_params(format!("post {uri} not found"), None))?;
+        let subject = strong_ref_from_uri(
+            &self.agent,
+            &post.uri,
+            Some(cid.unwrap_or(post.cid.clone())),
+        )
+        .await
+        .map_err(map_internal_err("failed to build post ref"))?;
+        Ok((subject, post.data.viewer))
+    }
+    /// Returns whether a call of a cacheable tool must not use the cache, because it records
+    /// state as a side effect.
+    fn bypasses_cache(&self, request: &CallToolRequestParam) -> bool {
+        match request.name.as_ref() {
+            // The counts are recorded on every call to report their deltas
+            "get_profile" => self.count_store.is_some(),
+            // New items are marked against the marker recorded by the previous call
+            "get_timeline" | "get_feed" | "get_list_feed" => request
+                .arguments
+                .as_ref()
+                .and_then(|arguments| arguments.get("mark_new"))
+                .and_then(Value::as_bool)
+                .unwrap_or_default(),
+            _ => false,
+        }
     }
+    /// Returns the repo to write to, which must be the current account.
+    async fn writable_repo(&self, repo: Option<&str>) -> Result<AtIdentifier, Error> {
+        let (did, handle) = self.identity().await?;
+        if repo
+            .is_some_and(|repo| repo != did.as_str() && !repo.eq_ignore_ascii_case(handle.as_str()))
+        {
+            return Err(Error::invalid_params(
+                "repo must be the handle or DID of the current account",
+                None,
+            ));
+        }
+        Ok(AtIdentifier::Did(did))
+    }
+}
+
+/// Media of a post embed, which can be combined with a quoted record.
+enum Media {
+    Images(bsky::embed::images::Main),
+    External(bsky::embed::external::Main),
+}
+
+/// Returns the requested embed depth, clamped to the maximum.
+fn embed_depth(embed_depth: Option<u8>) -> u8 {
+    embed_depth
+        .unwrap_or(DEFAULT_EMBED_DEPTH)
+        .min(MAX_EMBED_DEPTH)
 }
 
 #[tool(tool_box)]
 impl BskyService {
-    #[tool(description = "Get the current user DID.")]
+    #[tool(description = "Get the current user DID and handle.")]
     async fn get_did(&self) -> Result<CallToolResult, Error> {
-        Ok(if let Some(did) = self.agent.did().await {
-            CallToolResult::success(vec![Content::text(did.as_ref())])
-        } else {
-            CallToolResult::error(vec![Content::text("failed to get did")])
-        })
+        let (did, handle) = self.identity().await?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "did": did,
+            "handle": handle,
+        }))?]))
+    }
+    #[tool(description = "Clear cached tool results, so that subsequent reads fetch fresh data.")]
+    async fn clear_cache(&self) -> Result<CallToolResult, Error> {
+        self.cache.clear();
+        Ok(CallToolResult::success(vec![Content::text(
+            "cache cleared",
+        )]))
+    }
+    #[tool(
+        description = "Switch the active account to one of the configured accounts. Subsequent tools operate as the new account."
+    )]
+    async fn switch_account(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Alias of the configured account to switch to.")]
+        alias: String,
+    ) -> Result<CallToolResult, Error> {
+        if !self.config.account_switching {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "account switching is disabled",
+            )]));
+        }
+        let account = self
+            .config
+            .accounts
+            .get(&alias)
+            .ok_or(Error::invalid_params(
+                format!("unknown account alias: {alias}"),
+                None,
+            ))?;
+        let session = self
+            .agent
+            .login(&account.identifier, &account.password)
+            .await
+            .map_err(map_xrpc_err("failed to login"))?;
+        *self.identity.write().unwrap_or_else(|e| e.into_inner()) =
+            Some((session.did.clone(), session.handle.clone()));
+        tracing::info!(
+            "switched account to {} ({})",
+            session.handle.as_str(),
+            session.did.as_str()
+        );
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "did": session.did,
+            "handle": session.handle,
+        }))?]))
     }
     #[tool(description = "Get detailed profile view of an actor.")]
     async fn get_profile(
@@ -55,10 +599,18 @@ impl BskyService {
         #[tool(param)]
         #[schemars(description = "Handle or DID of account to fetch profile of")]
         actor: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Whether to also fetch the pinned post, returned as `pinnedPostView`."
+        )]
+        include_pinned_post: Option<bool>,
+        #[tool(param)]
+        #[schemars(
+            description = "Whether to also return the values of labels applied to the actor as `labelValues`."
+        )]
+        include_labels: Option<bool>,
     ) -> Result<CallToolResult, Error> {
-        let actor = actor.parse().map_err(|e: &str| {
-            Error::internal_error("failed to parse actor", Some(Value::String(e.into())))
-        })?;
+        let actor = self.parse_actor(&actor).await?;
         let profile = self
             .agent
             .api
@@ -67,425 +619,2721 @@ impl BskyService {
             .actor
             .get_profile(bsky::actor::get_profile::ParametersData { actor }.into())
             .await
-            .map_err(|e| {
-                Error::internal_error("failed to get profile", Some(Value::String(e.to_string())))
-            })?;
-        Ok(CallToolResult::success(vec![Content::json(
-            convert_datetime(profile).map_err(|e| {
-                Error::internal_error(
-                    "failed to convert datetime",
-                    Some(Value::String(e.to_string())),
+            .map_err(map_xrpc_err("failed to get profile"))?;
+        let pinned_post = match &profile.pinned_post {
+            Some(pinned_post) if include_pinned_post.unwrap_or_default() => self
+                .agent
+                .api
+                .app
+                .bsky
+                .feed
+                .get_posts(
+                    bsky::feed::get_posts::ParametersData {
+                        uris: vec![pinned_post.uri.clone()],
+                    }
+                    .into(),
                 )
-            })?,
-        )?]))
+                .await
+                .map_err(map_xrpc_err("failed to get pinned post"))?
+                .data
+                .posts
+                .pop(),
+            _ => None,
+        };
+        let mut value =
+            convert_datetime(&profile).map_err(map_internal_err("failed to convert datetime"))?;
+        if include_labels.unwrap_or_default() {
+            let mut label_values = Vec::new();
+            for label in profile.labels.iter().flatten() {
+                if !label_values.contains(&label.val) {
+                    label_values.push(label.val.clone());
+                }
+            }
+            value["labelValues"] = json!(label_values);
+        }
+        if let (Some(store), Some(followers_count), Some(follows_count)) = (
+            &self.count_store,
+            profile.followers_count,
+            profile.follows_count,
+        ) {
+            let observation = CountObservation {
+                followers_count,
+                follows_count,
+                observed_at: Utc::now().to_rfc3339(),
+            };
+            match store.record(profile.did.as_str(), observation) {
+                Ok(Some(previous)) => {
+                    value["countDelta"] = json!({
+                        "followers": followers_count - previous.followers_count,
+                        "follows": follows_count - previous.follows_count,
+                        "since": previous.observed_at,
+                    });
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("failed to record counts: {e:#}"),
+            }
+        }
+        if let (Some(pinned_post), Value::Object(map)) = (pinned_post, &mut value) {
+            map.insert(
+                "pinnedPostView".into(),
+                convert_datetime(pinned_post)
+                    .map_err(map_internal_err("failed to convert datetime"))?,
+            );
+        }
+        Ok(CallToolResult::success(vec![self.json_content(value)?]))
     }
     #[tool(
-        description = "Get a view of an actor's 'author feed' (post and reposts by the author)."
+        description = "Get detailed profile views of multiple actors, optionally as compact summaries of the relationship with the current user."
     )]
-    async fn get_author_feed(
+    async fn get_profiles(
         &self,
-        #[tool(aggr)] params: GetAuthorFeedParams,
+        #[tool(aggr)] params: GetProfilesParams,
     ) -> Result<CallToolResult, Error> {
-        let actor = params.actor.parse().map_err(|e: &str| {
-            Error::internal_error("failed to parse actor", Some(Value::String(e.into())))
-        })?;
-        let filter = if params.with_replies.unwrap_or_default() {
-            None
-        } else {
-            Some("posts_no_replies".into())
-        };
-        let limit = Some(
-            params
-                .limit
-                .unwrap_or(DEFAULT_LIMIT)
-                .try_into()
-                .map_err(|e| {
-                    Error::internal_error("failed to parse limit", Some(Value::String(e)))
-                })?,
-        );
+        if params.actors.len() > MAX_PROFILES {
+            return Err(Error::invalid_params(
+                format!("too many actors (max {MAX_PROFILES})"),
+                None,
+            ));
+        }
+        let mut actors = Vec::with_capacity(params.actors.len());
+        for actor in &params.actors {
+            actors.push(self.parse_actor(actor).await?);
+        }
         let output = self
             .agent
             .api
             .app
             .bsky
-            .feed
-            .get_author_feed(
-                bsky::feed::get_author_feed::ParametersData {
-                    actor,
-                    cursor: None,
-                    filter,
-                    include_pins: None,
-                    limit,
-                }
-                .into(),
-            )
+            .actor
+            .get_profiles(bsky::actor::get_profiles::ParametersData { actors }.into())
             .await
-            .map_err(|e| {
-                Error::internal_error(
-                    "failed to get author feed",
-                    Some(Value::String(e.to_string())),
-                )
-            })?;
-        Ok(CallToolResult::success(vec![Content::json(
-            convert_datetime(output.data.feed).map_err(|e| {
-                Error::internal_error(
-                    "failed to convert datetime",
-                    Some(Value::String(e.to_string())),
-                )
-            })?,
-        )?]))
+            .map_err(map_xrpc_err("failed to get profiles"))?;
+        if !params.relationships.unwrap_or_default() {
+            return Ok(CallToolResult::success(vec![
+                self.json_content(
+                    convert_datetime(output.data.profiles)
+                        .map_err(map_internal_err("failed to convert datetime"))?,
+                )?,
+            ]));
+        }
+        let summaries = output
+            .data
+            .profiles
+            .iter()
+            .map(|profile| {
+                let viewer = profile.viewer.as_ref();
+                json!({
+                    "did": profile.did,
+                    "handle": profile.handle,
+                    "displayName": profile.display_name,
+                    "relationship": {
+                        "following": viewer.is_some_and(|v| v.following.is_some()),
+                        "followedBy": viewer.is_some_and(|v| v.followed_by.is_some()),
+                        "blocking": viewer.is_some_and(|v| v.blocking.is_some()),
+                        "blockedBy": viewer.is_some_and(|v| v.blocked_by.unwrap_or_default()),
+                        "muted": viewer.is_some_and(|v| v.muted.unwrap_or_default()),
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(CallToolResult::success(vec![self.json_content(summaries)?]))
     }
-    #[tool(description = "Get posts in a thread.")]
-    async fn get_post_thread(
+    #[tool(
+        description = "Check whether a chat conversation can be started with an actor, based on their chat settings and relationship. Returns `canChat` with the `reason`."
+    )]
+    async fn get_convo_availability(
         &self,
-        #[tool(aggr)] params: GetPostThreadParams,
+        #[tool(param)]
+        #[schemars(description = "Handle or DID of account to check.")]
+        actor: String,
     ) -> Result<CallToolResult, Error> {
-        let depth = Some(
-            params
-                .depth
-                .unwrap_or(DEFAULT_DEPTH)
-                .try_into()
-                .map_err(|e| {
-                    Error::internal_error("failed to parse depth", Some(Value::String(e)))
-                })?,
-        );
-        let parent_height = Some(
-            params
-                .parent_height
-                .unwrap_or(DEFAULT_PARENT_HEIGHT)
-                .try_into()
-                .map_err(|e| {
-                    Error::internal_error("failed to parse parent height", Some(Value::String(e)))
-                })?,
-        );
-        let output = self
+        let actor = actor
+            .parse()
+            .map_err(map_invalid_params("failed to parse actor"))?;
+        let profile = self
             .agent
             .api
             .app
             .bsky
-            .feed
-            .get_post_thread(
-                bsky::feed::get_post_thread::ParametersData {
-                    depth,
-                    parent_height,
-                    uri: params.uri,
-                }
-                .into(),
-            )
+            .actor
+            .get_profile(bsky::actor::get_profile::ParametersData { actor }.into())
             .await
-            .map_err(|e| {
-                Error::internal_error(
-                    "failed to get post thread",
-                    Some(Value::String(e.to_string())),
-                )
-            })?;
-        Ok(CallToolResult::success(vec![Content::json(
-            convert_datetime(output.data).map_err(|e| {
-                Error::internal_error(
-                    "failed to convert datetime",
-                    Some(Value::String(e.to_string())),
-                )
-            })?,
-        )?]))
+            .map_err(map_xrpc_err("failed to get profile"))?;
+        let viewer = profile.viewer.as_ref();
+        // Accounts without a chat declaration only accept messages from accounts they follow
+        let allow_incoming = profile
+            .associated
+            .as_ref()
+            .and_then(|associated| associated.chat.as_ref())
+            .map_or("following", |chat| chat.allow_incoming.as_str());
+        let (can_chat, reason) = if viewer.is_some_and(|v| v.blocking.is_some()) {
+            (false, "you are blocking this account")
+        } else if viewer.is_some_and(|v| v.blocked_by.unwrap_or_default()) {
+            (false, "this account is blocking you")
+        } else {
+            match allow_incoming {
+                "all" => (true, "this account accepts messages from everyone"),
+                "none" => (false, "this account does not accept messages"),
+                _ if viewer.is_some_and(|v| v.followed_by.is_some()) => (
+                    true,
+                    "this account accepts messages from accounts it follows, including you",
+                ),
+                _ => (
+                    false,
+                    "this account only accepts messages from accounts it follows",
+                ),
+            }
+        };
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "did": profile.did,
+            "allowIncoming": allow_incoming,
+            "canChat": can_chat,
+            "reason": reason,
+        }))?]))
     }
-    #[tool(description = "Find posts matching search criteria, returning views of those posts.")]
-    async fn search_posts(
+    #[tool(
+        description = "Resolve a DID (`did:plc:` or `did:web:`) to its DID document, including the associated handle, PDS endpoint, and verification keys."
+    )]
+    async fn resolve_did(
         &self,
-        #[tool(aggr)] params: SearchPostsParams,
+        #[tool(param)]
+        #[schemars(description = "DID to resolve.")]
+        did: String,
     ) -> Result<CallToolResult, Error> {
-        let limit = Some(
-            params
-                .limit
-                .unwrap_or(DEFAULT_LIMIT)
-                .try_into()
-                .map_err(|e| {
-                    Error::internal_error("failed to parse limit", Some(Value::String(e)))
-                })?,
-        );
+        let did = did
+            .parse::<Did>()
+            .map_err(map_invalid_params("failed to parse did"))?;
+        let document = resolve_did_document(&self.http, &did)
+            .await
+            .map_err(map_internal_err("failed to resolve did document"))?;
+        let handle = document.also_known_as.as_ref().and_then(|aka| {
+            aka.iter()
+                .find_map(|uri| uri.strip_prefix("at://"))
+                .map(String::from)
+        });
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "did": did,
+            "handle": handle,
+            "pds": document.get_pds_endpoint(),
+            "verificationMethods": document.verification_method,
+            "document": document,
+        }))?]))
+    }
+    #[tool(
+        description = "Get a signed token on behalf of the current account, to be used to authenticate with another service."
+    )]
+    async fn get_service_auth(
+        &self,
+        #[tool(aggr)] params: GetServiceAuthParams,
+    ) -> Result<CallToolResult, Error> {
+        let aud = params
+            .aud
+            .parse()
+            .map_err(map_invalid_params("failed to parse aud"))?;
+        let lxm = params
+            .lxm
+            .map(|lxm| lxm.parse())
+            .transpose()
+            .map_err(map_invalid_params("failed to parse lxm"))?;
+        let exp = params
+            .expires_in
+            .map(|expires_in| Utc::now().timestamp() + expires_in);
+        // The token is a credential: it is returned to the client but never logged
         let output = self
             .agent
             .api
-            .app
-            .bsky
-            .feed
-            .search_posts(
-                bsky::feed::search_posts::ParametersData {
-                    author: None,
-                    cursor: None,
-                    domain: None,
-                    lang: None,
-                    limit,
-                    mentions: None,
-                    q: params.q,
-                    since: None,
-                    sort: None,
-                    tag: None,
-                    until: None,
-                    url: None,
-                }
-                .into(),
+            .com
+            .atproto
+            .server
+            .get_service_auth(
+                atproto::server::get_service_auth::ParametersData { aud, exp, lxm }.into(),
             )
             .await
-            .map_err(|e| {
-                Error::internal_error("failed to search posts", Some(Value::String(e.to_string())))
-            })?;
-        Ok(CallToolResult::success(vec![Content::json(
-            convert_datetime(output.data.posts).map_err(|e| {
-                Error::internal_error(
-                    "failed to convert datetime",
-                    Some(Value::String(e.to_string())),
-                )
-            })?,
-        )?]))
+            .map_err(map_xrpc_err("failed to get service auth"))?;
+        Ok(CallToolResult::success(vec![Content::text(
+            output.data.token,
+        )]))
     }
-    #[tool(description = "Enumerate notifications for the requesting account.")]
-    async fn list_notifications(
+    #[tool(
+        description = "Verify that a handle resolves to a DID whose DID document lists the same handle, detecting spoofed or misconfigured handles."
+    )]
+    async fn verify_handle(
         &self,
-        #[tool(aggr)] params: ListNotificationsParams,
+        #[tool(param)]
+        #[schemars(description = "The handle to verify.")]
+        handle: String,
     ) -> Result<CallToolResult, Error> {
-        Ok(CallToolResult::success(vec![Content::json(
-            convert_datetime(self._list_notifications(params).await?).map_err(|e| {
-                Error::internal_error(
-                    "failed to convert datetime",
-                    Some(Value::String(e.to_string())),
-                )
-            })?,
-        )?]))
+        let handle = handle
+            .trim_start_matches('@')
+            .parse::<Handle>()
+            .map_err(map_invalid_params("failed to parse handle"))?;
+        let output = self
+            .agent
+            .api
+            .com
+            .atproto
+            .identity
+            .resolve_handle(
+                atproto::identity::resolve_handle::ParametersData {
+                    handle: handle.clone(),
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to resolve handle"))?;
+        let document = resolve_did_document(&self.http, &output.data.did)
+            .await
+            .map_err(map_internal_err("failed to resolve did document"))?;
+        let document_handles = document
+            .also_known_as
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|uri| uri.strip_prefix("at://").map(String::from))
+            .collect::<Vec<_>>();
+        let verified = document_handles
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(handle.as_str()));
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "handle": handle,
+            "did": output.data.did,
+            "documentHandles": document_handles,
+            "verified": verified,
+        }))?]))
     }
     #[tool(
-        description = "Get the reply or mention notifications that have not been responded to by the user."
+        description = "Update the current account's handle, and report the DID the new handle resolves to. Only available if enabled by the server."
     )]
-    async fn get_unreplied_mentions(
+    async fn update_handle(
         &self,
         #[tool(param)]
-        #[schemars(description = "Maximum number of notifications to retrieve.")]
-        max_num: Option<u8>,
+        #[schemars(description = "The new handle.")]
+        handle: String,
     ) -> Result<CallToolResult, Error> {
-        // Get the recent notifications that are replies or mentions
-        let notifications = self
-            ._list_notifications(ListNotificationsParams {
-                limit: max_num,
-                reasons: vec![ReasonEnum::Mention, ReasonEnum::Reply],
-            })
-            .await?;
-        // Get the post thread for each notification concurrently
-        let mut handles = Vec::with_capacity(notifications.len());
-        for notification in notifications.iter() {
-            let agent = self.agent.clone();
-            let uri = notification.uri.clone();
-            handles.push(tokio::spawn(async move {
-                agent
+        if !self.config.handle_update {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "handle update is disabled",
+            )]));
+        }
+        let handle = handle
+            .trim_start_matches('@')
+            .parse::<Handle>()
+            .map_err(map_invalid_params("failed to parse handle"))?;
+        let did = self.did().await?;
+        self.agent
+            .api
+            .com
+            .atproto
+            .identity
+            .update_handle(
+                atproto::identity::update_handle::InputData {
+                    handle: handle.clone(),
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to update handle"))?;
+        tracing::info!("updated handle of {} to {}", did.as_str(), handle.as_str());
+        *self.identity.write().unwrap_or_else(|e| e.into_inner()) =
+            Some((did.clone(), handle.clone()));
+        // Confirm that the new handle resolves to the current account
+        let resolved = self
+            .agent
+            .api
+            .com
+            .atproto
+            .identity
+            .resolve_handle(
+                atproto::identity::resolve_handle::ParametersData {
+                    handle: handle.clone(),
+                }
+                .into(),
+            )
+            .await
+            .map(|output| output.data.did);
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "handle": handle,
+            "did": did,
+            "resolvedDid": resolved.as_ref().ok(),
+            "confirmed": resolved.as_ref().is_ok_and(|resolved| *resolved == did),
+        }))?]))
+    }
+    #[tool(
+        description = "Get the status of the current account's repo: activation, indexed and imported records and blobs, useful when migrating or diagnosing missing records."
+    )]
+    async fn check_account_status(&self) -> Result<CallToolResult, Error> {
+        let output = self
+            .agent
+            .api
+            .com
+            .atproto
+            .server
+            .check_account_status()
+            .await
+            .map_err(map_xrpc_err("failed to check account status"))?;
+        Ok(CallToolResult::success(vec![self.json_content(output)?]))
+    }
+    #[tool(description = "Get all invite codes for the current account, with their uses.")]
+    async fn get_account_invite_codes(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Whether to include invite codes that have already been used.")]
+        include_used: Option<bool>,
+    ) -> Result<CallToolResult, Error> {
+        let output = match self
+            .agent
+            .api
+            .com
+            .atproto
+            .server
+            .get_account_invite_codes(
+                atproto::server::get_account_invite_codes::ParametersData {
+                    create_available: None,
+                    include_used,
+                }
+                .into(),
+            )
+            .await
+        {
+            Ok(output) => output,
+            // The PDS responds with a 400 `InvitesDisabled` error when account invites are disabled
+            Err(e) if invites_disabled(&e) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "invite codes are not available for this account (invites are disabled on the PDS): {e}"
+                ))]));
+            }
+            Err(e) => return Err(xrpc_error("failed to get account invite codes", e)),
+        };
+        if output.data.codes.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "no invite codes found for this account",
+            )]));
+        }
+        Ok(CallToolResult::success(vec![
+            self.json_content(
+                convert_datetime(output.data.codes)
+                    .map_err(map_internal_err("failed to convert datetime"))?,
+            )?,
+        ]))
+    }
+    #[tool(
+        description = "Get a view of an actor's 'author feed' (post and reposts by the author)."
+    )]
+    async fn get_author_feed(
+        &self,
+        #[tool(aggr)] params: GetAuthorFeedParams,
+    ) -> Result<CallToolResult, Error> {
+        let actor = self.parse_actor(&params.actor).await?;
+        let filter = if params.with_replies.unwrap_or_default() {
+            None
+        } else {
+            Some("posts_no_replies".into())
+        };
+        let since = params
+            .since
+            .as_deref()
+            .map(parse_datetime)
+            .transpose()
+            .map_err(map_invalid_params("failed to parse since"))?;
+        let until = params
+            .until
+            .as_deref()
+            .map(parse_datetime)
+            .transpose()
+            .map_err(map_invalid_params("failed to parse until"))?;
+        let bounded = since.is_some() || until.is_some();
+        let max_items = usize::from(params.limit.unwrap_or(DEFAULT_LIMIT));
+        // The endpoint has no date bounds, so page through the feed with full pages
+        let limit = limit_to_param(if bounded { Some(100) } else { params.limit });
+        let mut items = Vec::new();
+        let mut cursor = None;
+        for _ in 0..MAX_AUTHOR_FEED_PAGES {
+            let output = self
+                .agent
+                .api
+                .app
+                .bsky
+                .feed
+                .get_author_feed(
+                    bsky::feed::get_author_feed::ParametersData {
+                        actor: actor.clone(),
+                        cursor: cursor.take(),
+                        filter: filter.clone(),
+                        include_pins: params.include_pins,
+                        limit,
+                    }
+                    .into(),
+                )
+                .await
+                .map_err(map_xrpc_err("failed to get author feed"))?;
+            let mut reached_since = false;
+            for item in output.data.feed {
+                // Items are ordered by the time they were posted or reposted, except pinned ones
+                let (indexed_at, pinned) = match &item.reason {
+                    Some(Union::Refs(bsky::feed::defs::FeedViewPostReasonRefs::ReasonRepost(
+                        reason,
+                    ))) => (reason.indexed_at.clone(), false),
+                    Some(Union::Refs(bsky::feed::defs::FeedViewPostReasonRefs::ReasonPin(_))) => {
+                        (item.post.indexed_at.clone(), true)
+                    }
+                    _ => (item.post.indexed_at.clone(), false),
+                };
+                if since
+                    .as_ref()
+                    .is_some_and(|since| indexed_at.as_ref() < since.as_ref())
+                {
+                    reached_since |= !pinned;
+                    continue;
+                }
+                if until
+                    .as_ref()
+                    .is_some_and(|until| indexed_at.as_ref() >= until.as_ref())
+                {
+                    continue;
+                }
+                items.push(item);
+            }
+            cursor = output.data.cursor;
+            if !bounded || reached_since || items.len() >= max_items || cursor.is_none() {
+                break;
+            }
+        }
+        items.truncate(max_items);
+        let mut feed =
+            convert_datetime(items).map_err(map_internal_err("failed to convert datetime"))?;
+        if let Some(reposts) = &params.reposts {
+            process_reposts(&mut feed, reposts);
+        }
+        hydrate_quotes(&self.agent, &mut feed, embed_depth(params.embed_depth)).await;
+        surface_videos(&mut feed);
+        Ok(CallToolResult::success(vec![self.json_content(feed)?]))
+    }
+    #[tool(description = "Get a view of the current user's home timeline.")]
+    async fn get_timeline(
+        &self,
+        #[tool(aggr)] params: GetTimelineParams,
+    ) -> Result<CallToolResult, Error> {
+        let limit = limit_to_param(params.limit);
+        let mut output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .feed
+            .get_timeline(
+                bsky::feed::get_timeline::ParametersData {
+                    algorithm: params
+                        .following_only
+                        .unwrap_or_default()
+                        .then(|| "reverse-chronological".into()),
+                    cursor: params.cursor,
+                    limit,
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to get timeline"))?;
+        if params.dedup.unwrap_or_default() {
+            let mut seen = HashSet::new();
+            output
+                .data
+                .feed
+                .retain(|item| seen.insert(item.post.uri.clone()));
+        }
+        let mut value = convert_datetime(output.data)
+            .map_err(map_internal_err("failed to convert datetime"))?;
+        hydrate_quotes(&self.agent, &mut value, embed_depth(params.embed_depth)).await;
+        surface_videos(&mut value);
+        let note = if params.mark_new.unwrap_or_default() {
+            Some(self.mark_new_items("timeline", &mut value).await?)
+        } else {
+            None
+        };
+        let mut contents = vec![self.json_content(value)?];
+        contents.extend(note.map(Content::text));
+        Ok(CallToolResult::success(contents))
+    }
+    #[tool(
+        description = "Get the current user's saved and pinned feeds (\"my feeds\"), pinned ones first, with their feed generator views."
+    )]
+    async fn get_my_feeds(&self) -> Result<CallToolResult, Error> {
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .actor
+            .get_preferences(bsky::actor::get_preferences::ParametersData {}.into())
+            .await
+            .map_err(map_xrpc_err("failed to get preferences"))?;
+        let mut saved_feeds = output
+            .data
+            .preferences
+            .into_iter()
+            .find_map(|preference| match preference {
+                Union::Refs(bsky::actor::defs::PreferencesItem::SavedFeedsPrefV2(pref)) => {
+                    Some(pref.data.items)
+                }
+                _ => None,
+            })
+            .unwrap_or_default();
+        // Pinned feeds come first, keeping the order within pinned and saved feeds
+        saved_feeds.sort_by_key(|saved_feed| !saved_feed.pinned);
+        let feeds = saved_feeds
+            .iter()
+            .filter(|saved_feed| saved_feed.r#type == "feed")
+            .map(|saved_feed| saved_feed.value.clone())
+            .collect::<Vec<_>>();
+        let mut views = HashMap::new();
+        if !feeds.is_empty() {
+            let output = self
+                .agent
+                .api
+                .app
+                .bsky
+                .feed
+                .get_feed_generators(
+                    bsky::feed::get_feed_generators::ParametersData { feeds }.into(),
+                )
+                .await
+                .map_err(map_xrpc_err("failed to get feed generators"))?;
+            for view in output.data.feeds {
+                views.insert(
+                    view.uri.clone(),
+                    convert_datetime(view)
+                        .map_err(map_internal_err("failed to convert datetime"))?,
+                );
+            }
+        }
+        Ok(CallToolResult::success(vec![
+            self.json_content(
+                saved_feeds
+                    .iter()
+                    .map(|saved_feed| {
+                        json!({
+                            "type": saved_feed.r#type,
+                            "value": saved_feed.value,
+                            "pinned": saved_feed.pinned,
+                            "view": views.get(&saved_feed.value),
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            )?,
+        ]))
+    }
+    #[tool(
+        description = "Update the current user's view preferences of a feed (e.g. hiding replies or reposts), keeping the other preferences."
+    )]
+    async fn set_feed_view_pref(
+        &self,
+        #[tool(aggr)] params: SetFeedViewPrefParams,
+    ) -> Result<CallToolResult, Error> {
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .actor
+            .get_preferences(bsky::actor::get_preferences::ParametersData {}.into())
+            .await
+            .map_err(map_xrpc_err("failed to get preferences"))?;
+        // Edit the preferences as JSON, so that unknown preferences are kept as they are
+        let Value::Array(mut preferences) = serde_json::to_value(&output.data.preferences)
+            .map_err(map_internal_err("failed to serialize preferences"))?
+        else {
+            return Err(Error::internal_error("unexpected preferences", None));
+        };
+        let feed = params.feed.unwrap_or_else(|| "home".into());
+        let index = preferences
+            .iter()
+            .position(|pref| {
+                pref["$type"] == "app.bsky.actor.defs#feedViewPref" && pref["feed"] == feed
+            })
+            .unwrap_or_else(|| {
+                preferences.push(json!({
+                    "$type": "app.bsky.actor.defs#feedViewPref",
+                    "feed": feed,
+                }));
+                preferences.len() - 1
+            });
+        let Value::Object(pref) = &mut preferences[index] else {
+            return Err(Error::internal_error(
+                "unexpected feed view preference",
+                None,
+            ));
+        };
+        for (key, value) in [
+            ("hideReplies", params.hide_replies),
+            ("hideRepliesByUnfollowed", params.hide_replies_by_unfollowed),
+            ("hideReposts", params.hide_reposts),
+            ("hideQuotePosts", params.hide_quote_posts),
+        ] {
+            if let Some(value) = value {
+                pref.insert(key.into(), Value::Bool(value));
+            }
+        }
+        let updated = Value::Object(pref.clone());
+        let preferences = serde_json::from_value(Value::Array(preferences))
+            .map_err(map_internal_err("failed to parse preferences"))?;
+        self.agent
+            .api
+            .app
+            .bsky
+            .actor
+            .put_preferences(bsky::actor::put_preferences::InputData { preferences }.into())
+            .await
+            .map_err(map_xrpc_err("failed to put preferences"))?;
+        Ok(CallToolResult::success(vec![self.json_content(updated)?]))
+    }
+    #[tool(
+        description = "Get views of multiple feed generators. Feeds that could not be found (invalid or offline) are reported as `missing`."
+    )]
+    async fn get_feed_generators(
+        &self,
+        #[tool(aggr)] params: GetFeedGeneratorsParams,
+    ) -> Result<CallToolResult, Error> {
+        let mut feeds = Vec::with_capacity(params.feeds.len());
+        for feed in &params.feeds {
+            feeds.push(self.normalize_ref(feed).await?);
+        }
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .feed
+            .get_feed_generators(
+                bsky::feed::get_feed_generators::ParametersData {
+                    feeds: feeds.clone(),
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to get feed generators"))?;
+        // The response silently omits feeds that could not be resolved
+        let found = output
+            .data
+            .feeds
+            .iter()
+            .map(|view| view.uri.as_str())
+            .collect::<HashSet<_>>();
+        let missing = feeds
+            .iter()
+            .filter(|feed| !found.contains(feed.as_str()))
+            .collect::<Vec<_>>();
+        let feeds = convert_datetime(&output.data.feeds)
+            .map_err(map_internal_err("failed to convert datetime"))?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "feeds": feeds,
+            "missing": missing,
+        }))?]))
+    }
+    #[tool(description = "Get a hydrated feed from a custom feed generator.")]
+    async fn get_feed(&self, #[tool(aggr)] params: GetFeedParams) -> Result<CallToolResult, Error> {
+        let feed = self.normalize_ref(&params.feed).await?;
+        if params.check_generator.unwrap_or(true) {
+            let output = self
+                .agent
+                .api
+                .app
+                .bsky
+                .feed
+                .get_feed_generator(
+                    bsky::feed::get_feed_generator::ParametersData { feed: feed.clone() }.into(),
+                )
+                .await
+                .map_err(map_xrpc_err("failed to get feed generator"))?;
+            if !output.data.is_online || !output.data.is_valid {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "feed {} is {}",
+                    output.data.view.display_name,
+                    if output.data.is_online {
+                        "invalid"
+                    } else {
+                        "offline"
+                    }
+                ))]));
+            }
+        }
+        let limit = limit_to_param(params.limit);
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .feed
+            .get_feed(
+                bsky::feed::get_feed::ParametersData {
+                    cursor: params.cursor,
+                    feed: feed.clone(),
+                    limit,
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to get feed"))?;
+        let mut value = convert_datetime(output.data)
+            .map_err(map_internal_err("failed to convert datetime"))?;
+        surface_videos(&mut value);
+        let note = if params.mark_new.unwrap_or_default() {
+            Some(self.mark_new_items(&feed, &mut value).await?)
+        } else {
+            None
+        };
+        let mut contents = vec![self.json_content(value)?];
+        contents.extend(note.map(Content::text));
+        Ok(CallToolResult::success(contents))
+    }
+    #[tool(
+        description = "Get a feed of recent posts from the members of a list, optionally excluding their reposts."
+    )]
+    async fn get_list_feed(
+        &self,
+        #[tool(aggr)] params: GetListFeedParams,
+    ) -> Result<CallToolResult, Error> {
+        let list = self.normalize_ref(&params.list).await?;
+        let limit = limit_to_param(params.limit);
+        let mut output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .feed
+            .get_list_feed(
+                bsky::feed::get_list_feed::ParametersData {
+                    cursor: params.cursor,
+                    limit,
+                    list: list.clone(),
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to get list feed"))?;
+        if params.exclude_reposts.unwrap_or_default() {
+            // The cursor still points past the removed reposts, so pagination is not affected
+            output.data.feed.retain(|item| {
+                !matches!(
+                    item.reason,
+                    Some(Union::Refs(
+                        bsky::feed::defs::FeedViewPostReasonRefs::ReasonRepost(_)
+                    ))
+                )
+            });
+        }
+        let mut value = convert_datetime(output.data)
+            .map_err(map_internal_err("failed to convert datetime"))?;
+        surface_videos(&mut value);
+        let note = if params.mark_new.unwrap_or_default() {
+            Some(self.mark_new_items(&list, &mut value).await?)
+        } else {
+            None
+        };
+        let mut contents = vec![self.json_content(value)?];
+        contents.extend(note.map(Content::text));
+        Ok(CallToolResult::success(contents))
+    }
+    #[tool(
+        description = "Send feedback on interactions with posts from a custom feed (e.g. seen, clicked, liked), which the feed generator may use to personalize the feed."
+    )]
+    async fn send_interactions(
+        &self,
+        #[tool(aggr)] params: SendInteractionsParams,
+    ) -> Result<CallToolResult, Error> {
+        let feed = self.normalize_ref(&params.feed).await?;
+        // Interactions are sent to the feed generator service, through the PDS
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .feed
+            .get_feed_generator(bsky::feed::get_feed_generator::ParametersData { feed }.into())
+            .await
+            .map_err(map_xrpc_err("failed to get feed generator"))?;
+        let mut interactions = Vec::with_capacity(params.interactions.len());
+        for interaction in &params.interactions {
+            interactions.push(json!({
+                "item": self.normalize_ref(&interaction.uri).await?,
+                "event": interaction.event.to_string(),
+                "feedContext": params.feed_context,
+            }));
+        }
+        let input = serde_json::from_value::<bsky::feed::send_interactions::InputData>(json!({
+            "interactions": interactions,
+        }))
+        .map_err(map_internal_err("failed to build interactions"))?;
+        self.agent
+            .api_with_proxy(output.data.view.did.clone(), "bsky_fg")
+            .app
+            .bsky
+            .feed
+            .send_interactions(input.into())
+            .await
+            .map_err(map_xrpc_err("failed to send interactions"))?;
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "sent {} interaction(s) to the feed generator {}",
+            params.interactions.len(),
+            output.data.view.did.as_str()
+        ))]))
+    }
+    #[tool(description = "Get posts in a thread.")]
+    async fn get_post_thread(
+        &self,
+        #[tool(aggr)] params: GetPostThreadParams,
+    ) -> Result<CallToolResult, Error> {
+        let uri = self.normalize_ref(&params.uri).await?;
+        // Clamp to the maxima accepted by the API instead of letting the request be rejected
+        let mut notes = Vec::new();
+        let (depth, depth_note) = clamp_param(
+            "depth",
+            params.depth.or(params.context).unwrap_or(DEFAULT_DEPTH),
+            MAX_DEPTH,
+        );
+        let (parent_height, parent_height_note) = clamp_param(
+            "parent_height",
+            params
+                .parent_height
+                .or(params.context)
+                .unwrap_or(DEFAULT_PARENT_HEIGHT),
+            MAX_PARENT_HEIGHT,
+        );
+        notes.extend(depth_note.into_iter().chain(parent_height_note));
+        let depth =
+            Some(depth.try_into().map_err(|e| {
+                Error::internal_error("failed to parse depth", Some(Value::String(e)))
+            })?);
+        let parent_height = Some(parent_height.try_into().map_err(|e| {
+            Error::internal_error("failed to parse parent height", Some(Value::String(e)))
+        })?);
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .feed
+            .get_post_thread(
+                bsky::feed::get_post_thread::ParametersData {
+                    depth,
+                    parent_height,
+                    uri,
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to get post thread"))?;
+        let mut value = convert_datetime(output.data)
+            .map_err(map_internal_err("failed to convert datetime"))?;
+        if params.replied_by_me.unwrap_or_default() {
+            let did = self.did().await?;
+            annotate_replied_by(&mut value["thread"], did.as_str());
+        }
+        if let Some(max_replies) = params.max_replies_per_node {
+            limit_replies(&mut value["thread"], max_replies);
+        }
+        let (not_found, blocked) = count_unavailable_posts(&value["thread"]);
+        value["unavailablePosts"] = json!({ "notFound": not_found, "blocked": blocked });
+        if not_found + blocked > 0 {
+            notes.push(format!(
+                "{not_found} post(s) in the thread were not found and {blocked} were blocked, so the conversation may have gaps."
+            ));
+        }
+        let format = params.format.unwrap_or_default();
+        if matches!(format, ThreadFormatEnum::Json) {
+            hydrate_quotes(&self.agent, &mut value, embed_depth(params.embed_depth)).await;
+            surface_videos(&mut value);
+        }
+        // Truncate last, so that the hydrated embeds count towards the size
+        if truncate_thread(&mut value["thread"], self.config.max_thread_size) {
+            value["truncated"] = Value::Bool(true);
+            notes.push(format!(
+                "the thread was truncated to fit in {} bytes; deeper replies were removed.",
+                self.config.max_thread_size
+            ));
+        }
+        let mut contents = vec![match format {
+            ThreadFormatEnum::Json => self.json_content(value)?,
+            ThreadFormatEnum::Transcript => {
+                Content::text(render_thread_transcript(&value["thread"]))
+            }
+        }];
+        contents.extend(notes.into_iter().map(Content::text));
+        Ok(CallToolResult::success(contents))
+    }
+    #[tool(description = "Find posts matching search criteria, returning views of those posts.")]
+    async fn search_posts(
+        &self,
+        #[tool(aggr)] params: SearchPostsParams,
+    ) -> Result<CallToolResult, Error> {
+        let limit = limit_to_param(params.limit);
+        let since = params
+            .since
+            .as_deref()
+            .map(parse_datetime)
+            .transpose()
+            .map_err(map_invalid_params("failed to parse since"))?;
+        let until = params
+            .until
+            .as_deref()
+            .map(parse_datetime)
+            .transpose()
+            .map_err(map_invalid_params("failed to parse until"))?;
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .feed
+            .search_posts(
+                bsky::feed::search_posts::ParametersData {
+                    author: None,
+                    cursor: params.cursor,
+                    domain: None,
+                    lang: None,
+                    limit,
+                    mentions: None,
+                    q: params.q,
+                    since: since.map(|since| since.as_str().into()),
+                    sort: None,
+                    tag: None,
+                    until: until.map(|until| until.as_str().into()),
+                    url: None,
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to search posts"))?;
+        let cursor = output.data.cursor;
+        let mut posts = output.data.posts;
+        rank_posts(&mut posts, &params.rank_by.unwrap_or_default());
+        let mut posts =
+            convert_datetime(posts).map_err(map_internal_err("failed to convert datetime"))?;
+        hydrate_quotes(&self.agent, &mut posts, embed_depth(params.embed_depth)).await;
+        surface_videos(&mut posts);
+        if params.include_parent.unwrap_or_default() {
+            self.add_parent_context(&mut posts).await;
+        }
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "posts": posts,
+            "cursor": cursor,
+        }))?]))
+    }
+    #[tool(description = "Get mod lists that the requesting account (actor) is blocking.")]
+    async fn get_list_blocks(
+        &self,
+        #[tool(aggr)] params: GetListBlocksParams,
+    ) -> Result<CallToolResult, Error> {
+        let limit = limit_to_param(params.limit);
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .graph
+            .get_list_blocks(
+                bsky::graph::get_list_blocks::ParametersData {
+                    cursor: params.cursor,
+                    limit,
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to get list blocks"))?;
+        Ok(CallToolResult::success(vec![
+            self.json_content(
+                convert_datetime(output.data)
+                    .map_err(map_internal_err("failed to convert datetime"))?,
+            )?,
+        ]))
+    }
+    #[tool(
+        description = "Enumerate mod lists that the requesting account (actor) currently has muted."
+    )]
+    async fn get_list_mutes(
+        &self,
+        #[tool(aggr)] params: GetListMutesParams,
+    ) -> Result<CallToolResult, Error> {
+        let limit = limit_to_param(params.limit);
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .graph
+            .get_list_mutes(
+                bsky::graph::get_list_mutes::ParametersData {
+                    cursor: params.cursor,
+                    limit,
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to get list mutes"))?;
+        Ok(CallToolResult::success(vec![
+            self.json_content(
+                convert_datetime(output.data)
+                    .map_err(map_internal_err("failed to convert datetime"))?,
+            )?,
+        ]))
+    }
+    #[tool(
+        description = "Get the starter packs created by an actor, with their names, descriptions, and member counts."
+    )]
+    async fn get_actor_starter_packs(
+        &self,
+        #[tool(aggr)] params: GetActorStarterPacksParams,
+    ) -> Result<CallToolResult, Error> {
+        let actor = self.parse_actor(&params.actor).await?;
+        let limit = limit_to_param(params.limit);
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .graph
+            .get_actor_starter_packs(
+                bsky::graph::get_actor_starter_packs::ParametersData {
+                    actor,
+                    cursor: params.cursor,
+                    limit,
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to get actor starter packs"))?;
+        let starter_packs = output
+            .data
+            .starter_packs
+            .iter()
+            .map(|starter_pack| {
+                let record = serde_json::to_value(&starter_pack.record).unwrap_or_default();
+                json!({
+                    "uri": starter_pack.uri,
+                    "name": record.get("name"),
+                    "description": record.get("description"),
+                    "listItemCount": starter_pack.list_item_count,
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "starterPacks": starter_packs,
+            "cursor": output.data.cursor,
+        }))?]))
+    }
+    #[tool(
+        description = "Get the mutual accounts of an actor, which both follow and are followed by the actor. Large graphs are fetched partially, and the response is marked `truncated`."
+    )]
+    async fn get_mutuals(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Handle or DID of the actor.")]
+        actor: String,
+    ) -> Result<CallToolResult, Error> {
+        let actor = actor
+            .parse()
+            .map_err(map_invalid_params("failed to parse actor"))?;
+        // Fetch both sides of the graph concurrently
+        let ((followers, followers_truncated), (follows, follows_truncated)) = futures::try_join!(
+            self.graph_accounts(&actor, true),
+            self.graph_accounts(&actor, false)
+        )?;
+        let followers = followers
+            .iter()
+            .map(|follower| follower.did.as_str())
+            .collect::<HashSet<_>>();
+        let mutuals = follows
+            .iter()
+            .filter(|follow| followers.contains(follow.did.as_str()))
+            .collect::<Vec<_>>();
+        let truncated = followers_truncated || follows_truncated;
+        let mut contents = vec![self.json_content(json!({
+            "mutuals": convert_datetime(&mutuals).map_err(map_internal_err("failed to convert datetime"))?,
+            "truncated": truncated,
+        }))?];
+        if truncated {
+            contents.push(Content::text(
+                "the followers or follows were too many to fetch completely; some mutual accounts may be missing.",
+            ));
+        }
+        Ok(CallToolResult::success(contents))
+    }
+    #[tool(
+        description = "Get a page of the accounts following an actor. Pass the returned `cursor` to fetch the next page."
+    )]
+    async fn get_followers(
+        &self,
+        #[tool(aggr)] params: GetFollowersParams,
+    ) -> Result<CallToolResult, Error> {
+        let actor = self.parse_actor(&params.actor).await?;
+        let limit = limit_to_param(params.limit);
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .graph
+            .get_followers(
+                bsky::graph::get_followers::ParametersData {
+                    actor,
+                    cursor: params.cursor,
+                    limit,
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to get followers"))?;
+        let followers = convert_datetime(output.data.followers)
+            .map_err(map_internal_err("failed to convert datetime"))?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "followers": followers,
+            "cursor": output.data.cursor,
+        }))?]))
+    }
+    #[tool(
+        description = "Get a page of the accounts followed by an actor, along with the actor's own profile as `subject`. Pass the returned `cursor` to fetch the next page."
+    )]
+    async fn get_follows(
+        &self,
+        #[tool(aggr)] params: GetFollowsParams,
+    ) -> Result<CallToolResult, Error> {
+        let actor = self.parse_actor(&params.actor).await?;
+        let limit = limit_to_param(params.limit);
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .graph
+            .get_follows(
+                bsky::graph::get_follows::ParametersData {
+                    actor,
+                    cursor: params.cursor,
+                    limit,
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to get follows"))?;
+        // Keep the subject, so that its display name is available as well as its DID
+        let subject = convert_datetime(output.data.subject)
+            .map_err(map_internal_err("failed to convert datetime"))?;
+        let follows = convert_datetime(output.data.follows)
+            .map_err(map_internal_err("failed to convert datetime"))?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "subject": subject,
+            "follows": follows,
+            "cursor": output.data.cursor,
+        }))?]))
+    }
+    #[tool(
+        description = "Follow every given account and/or every member of a list, skipping accounts already followed. Returns counts of followed and skipped accounts, and the failures. Members of very large lists are fetched partially."
+    )]
+    async fn follow_all(
+        &self,
+        #[tool(aggr)] params: FollowAllParams,
+    ) -> Result<CallToolResult, Error> {
+        let did = self.did().await?;
+        let mut actors = params.actors.unwrap_or_default();
+        let mut list_truncated = false;
+        if let Some(list) = &params.list {
+            let list = self.normalize_ref(list).await?;
+            let mut cursor = None;
+            for _ in 0..MAX_GRAPH_PAGES {
+                let output = self
+                    .agent
                     .api
                     .app
                     .bsky
-                    .feed
-                    .get_post_thread(
-                        bsky::feed::get_post_thread::ParametersData {
-                            depth: 1.try_into().ok(),
-                            parent_height: Some(LimitedU16::MIN),
-                            uri,
+                    .graph
+                    .get_list(
+                        bsky::graph::get_list::ParametersData {
+                            cursor,
+                            limit: limit_to_param(Some(100)),
+                            list: list.clone(),
                         }
                         .into(),
                     )
                     .await
+                    .map_err(map_xrpc_err("failed to get list"))?;
+                actors.extend(
+                    output
+                        .data
+                        .items
+                        .iter()
+                        .map(|item| item.subject.did.to_string()),
+                );
+                cursor = output.data.cursor;
+                if cursor.is_none() {
+                    break;
+                }
+            }
+            list_truncated = cursor.is_some();
+        }
+        let mut seen = HashSet::new();
+        actors.retain(|actor| seen.insert(actor.trim_start_matches('@').to_lowercase()));
+        if actors.is_empty() {
+            return Err(Error::invalid_params(
+                "either actors or a non-empty list is required",
+                None,
+            ));
+        }
+        // Unparsable actors fail on their own, without aborting the others
+        let mut failed = Vec::new();
+        let mut identifiers = Vec::new();
+        for actor in &actors {
+            let actor = actor.trim_start_matches('@');
+            match actor.parse::<AtIdentifier>() {
+                Ok(identifier) => identifiers.push((actor, identifier)),
+                Err(e) => failed.push(json!({
+                    "actor": actor,
+                    "error": format!("failed to parse actor: {e}"),
+                })),
+            }
+        }
+        // Resolve the actors with their relationships to find the ones to follow
+        let mut subjects = Vec::new();
+        let mut skipped = 0;
+        for chunk in identifiers.chunks(MAX_PROFILES) {
+            let output = self
+                .agent
+                .api
+                .app
+                .bsky
+                .actor
+                .get_profiles(
+                    bsky::actor::get_profiles::ParametersData {
+                        actors: chunk
+                            .iter()
+                            .map(|(_, identifier)| identifier.clone())
+                            .collect(),
+                    }
+                    .into(),
+                )
+                .await
+                .map_err(map_xrpc_err("failed to get profiles"))?;
+            for &(actor, _) in chunk {
+                let Some(profile) = output.data.profiles.iter().find(|profile| {
+                    profile.did.as_str() == actor
+                        || profile.handle.as_str().eq_ignore_ascii_case(actor)
+                }) else {
+                    failed.push(json!({ "actor": actor, "error": "profile not found" }));
+                    continue;
+                };
+                let following = profile
+                    .viewer
+                    .as_ref()
+                    .is_some_and(|viewer| viewer.following.is_some());
+                if following || profile.did == did {
+                    skipped += 1;
+                } else {
+                    subjects.push(profile.did.clone());
+                }
+            }
+        }
+        // Create the follow records in batches, within the limit of writes per request
+        let mut followed = 0;
+        for chunk in subjects.chunks(100) {
+            let created_at = Datetime::now();
+            let input = serde_json::from_value::<atproto::repo::apply_writes::InputData>(json!({
+                "repo": did,
+                "writes": chunk
+                    .iter()
+                    .map(|subject| {
+                        json!({
+                            "$type": "com.atproto.repo.applyWrites#create",
+                            "collection": "app.bsky.graph.follow",
+                            "value": {
+                                "$type": "app.bsky.graph.follow",
+                                "subject": subject,
+                                "createdAt": created_at,
+                            },
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            }))
+            .map_err(map_internal_err("failed to build writes"))?;
+            match self
+                .agent
+                .api
+                .com
+                .atproto
+                .repo
+                .apply_writes(input.into())
+                .await
+            {
+                Ok(_) => followed += chunk.len(),
+                Err(e) => failed.extend(
+                    chunk
+                        .iter()
+                        .map(|subject| json!({ "actor": subject, "error": e.to_string() })),
+                ),
+            }
+        }
+        let mut contents = vec![self.json_content(json!({
+            "followed": followed,
+            "skipped": skipped,
+            "failed": failed,
+        }))?];
+        if list_truncated {
+            contents.push(Content::text(
+                "the list had too many members to fetch completely; only the fetched members were followed.",
+            ));
+        }
+        Ok(CallToolResult::success(contents))
+    }
+    #[tool(
+        description = "Get suggested accounts to follow for a topic or interest keyword, from the tagged suggestions of the service."
+    )]
+    async fn get_topic_suggestions(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Topic or interest keyword (e.g. `art`, `science`).")]
+        topic: String,
+    ) -> Result<CallToolResult, Error> {
+        // The endpoint is unspecced, so it may not be available on every service
+        let output = match self
+            .agent
+            .api
+            .app
+            .bsky
+            .unspecced
+            .get_tagged_suggestions(
+                bsky::unspecced::get_tagged_suggestions::ParametersData {}.into(),
+            )
+            .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                tracing::warn!("failed to get tagged suggestions: {e}");
+                return Ok(CallToolResult::error(vec![Content::text(
+                    "topic suggestions are not available from the service",
+                )]));
+            }
+        };
+        let topic = topic.trim().trim_start_matches('#').to_lowercase();
+        let mut tags = output
+            .data
+            .suggestions
+            .iter()
+            .map(|suggestion| suggestion.tag.as_str())
+            .collect::<Vec<_>>();
+        tags.sort_unstable();
+        tags.dedup();
+        let matched = tags
+            .iter()
+            .filter(|tag| tag.to_lowercase().contains(&topic))
+            .copied()
+            .collect::<HashSet<_>>();
+        if matched.is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "no suggestions for the topic; available topics: {}",
+                tags.join(", ")
+            ))]));
+        }
+        let mut suggestions = Vec::new();
+        for tag in tags.iter().filter(|tag| matched.contains(*tag)) {
+            let actors = output
+                .data
+                .suggestions
+                .iter()
+                .filter(|suggestion| suggestion.tag == *tag && suggestion.subject_type == "actor")
+                .filter_map(|suggestion| suggestion.subject.parse().ok())
+                .take(MAX_PROFILES)
+                .collect::<Vec<_>>();
+            if actors.is_empty() {
+                continue;
+            }
+            let profiles = self
+                .agent
+                .api
+                .app
+                .bsky
+                .actor
+                .get_profiles(bsky::actor::get_profiles::ParametersData { actors }.into())
+                .await
+                .map_err(map_xrpc_err("failed to get profiles"))?;
+            suggestions.push(json!({
+                "tag": tag,
+                "actors": convert_datetime(&profiles.data.profiles).map_err(map_internal_err("failed to convert datetime"))?,
             }));
         }
-        let did = self
+        Ok(CallToolResult::success(vec![
+            self.json_content(suggestions)?,
+        ]))
+    }
+    #[tool(description = "Enumerate notifications for the requesting account.")]
+    async fn list_notifications(
+        &self,
+        #[tool(aggr)] params: ListNotificationsParams,
+    ) -> Result<CallToolResult, Error> {
+        Ok(CallToolResult::success(vec![
+            self.json_content(
+                convert_datetime(self._list_notifications(params).await?)
+                    .map_err(map_internal_err("failed to convert datetime"))?,
+            )?,
+        ]))
+    }
+    #[tool(
+        description = "Get the reply or mention notifications that have not been responded to by the user."
+    )]
+    async fn get_unreplied_mentions(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Maximum number of notifications to retrieve.")]
+        max_num: Option<u8>,
+        #[tool(param)]
+        #[schemars(
+            description = "Whether to also include notifications that the user has replied to, if the thread has received newer replies from others since the user's last reply. Their count is reported as `newRepliesSinceMyReply`."
+        )]
+        include_new_replies: Option<bool>,
+    ) -> Result<CallToolResult, Error> {
+        // Get the recent notifications that are replies or mentions
+        let notifications = self
+            ._list_notifications(ListNotificationsParams {
+                limit: max_num,
+                reasons: vec![ReasonEnum::Mention, ReasonEnum::Reply],
+                priority: None,
+                since: None,
+                until: None,
+                seen_at: None,
+            })
+            .await?;
+        // Get the post thread for each notification concurrently, each bounded by a deadline
+        let mut handles = Vec::with_capacity(notifications.len());
+        for notification in notifications.iter() {
+            let agent = self.agent.clone();
+            let uri = notification.uri.clone();
+            let deadline = self.config.mention_thread_timeout;
+            handles.push((
+                notification.uri.clone(),
+                tokio::spawn(async move {
+                    tokio::time::timeout(
+                        deadline,
+                        agent.api.app.bsky.feed.get_post_thread(
+                            bsky::feed::get_post_thread::ParametersData {
+                                depth: MENTION_THREAD_DEPTH.try_into().ok(),
+                                parent_height: Some(LimitedU16::MIN),
+                                uri,
+                            }
+                            .into(),
+                        ),
+                    )
+                    .await
+                }),
+            ));
+        }
+        let did = self.did().await?;
+        // Collect the uris of posts that have been replied from the current user,
+        // with the number of replies from others newer than the user's last reply
+        let mut replied = HashMap::new();
+        // Collect the uris of posts whose thread could not be fetched in time
+        let mut skipped = HashSet::new();
+        for (uri, handle) in handles {
+            // Wait for the task to finish and get the result
+            let Ok(result) = handle
+                .await
+                .map_err(map_internal_err("failed to await task"))?
+            else {
+                tracing::warn!("timed out getting post thread of {uri}, skipping");
+                skipped.insert(uri);
+                continue;
+            };
+            let output = result.map_err(map_xrpc_err("failed to get post thread"))?;
+            // Check if the thread contains a reply from the user
+            let thread = serde_json::to_value(&output.thread)
+                .map_err(map_internal_err("failed to serialize thread"))?;
+            if let (Some(uri), Some(new_replies)) = (
+                thread.pointer("/post/uri").and_then(Value::as_str),
+                new_replies_since(&thread, did.as_str()),
+            ) {
+                replied.insert(uri.to_string(), new_replies);
+            }
+        }
+        // Filter the notifications to only include those that have not been replied to
+        // (or that have new replies since, if requested)
+        let include_new_replies = include_new_replies.unwrap_or_default();
+        let unreplied = notifications
+            .iter()
+            .filter(|notification| {
+                !skipped.contains(&notification.uri)
+                    && replied
+                        .get(&notification.uri)
+                        .is_none_or(|&new_replies| include_new_replies && new_replies > 0)
+            })
+            .collect::<Vec<_>>();
+        let mut value =
+            convert_datetime(&unreplied).map_err(map_internal_err("failed to convert datetime"))?;
+        if let Value::Array(items) = &mut value {
+            for (item, notification) in items.iter_mut().zip(&unreplied) {
+                if let (Value::Object(map), Some(&new_replies)) =
+                    (item, replied.get(&notification.uri))
+                {
+                    map.insert("newRepliesSinceMyReply".into(), new_replies.into());
+                }
+            }
+        }
+        let mut contents = vec![self.json_content(value)?];
+        if !skipped.is_empty() {
+            contents.push(Content::text(format!(
+                "{} notification(s) were skipped because their threads could not be fetched in time.",
+                skipped.len()
+            )));
+        }
+        Ok(CallToolResult::success(contents))
+    }
+    async fn _list_notifications(
+        &self,
+        params: ListNotificationsParams,
+    ) -> Result<Vec<bsky::notification::list_notifications::Notification>, Error> {
+        let since = params
+            .since
+            .as_deref()
+            .map(parse_datetime)
+            .transpose()
+            .map_err(map_invalid_params("failed to parse since"))?;
+        let until = params
+            .until
+            .as_deref()
+            .map(parse_datetime)
+            .transpose()
+            .map_err(map_invalid_params("failed to parse until"))?;
+        let seen_at = params
+            .seen_at
+            .as_deref()
+            .map(parse_datetime)
+            .transpose()
+            .map_err(map_invalid_params("failed to parse seen_at"))?;
+        let limit = limit_to_param(params.limit);
+        let reasons = if !params.reasons.is_empty() {
+            Some(params.reasons.iter().map(|r| r.to_string()).collect())
+        } else if !self.config.notification_reasons.is_empty() {
+            Some(self.config.notification_reasons.iter().cloned().collect())
+        } else {
+            None
+        };
+        let output = self
+            .agent
+            .api
+            .app
+            .bsky
+            .notification
+            .list_notifications(
+                bsky::notification::list_notifications::ParametersData {
+                    cursor: None,
+                    limit,
+                    priority: params.priority,
+                    reasons,
+                    seen_at,
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to list notifications"))?;
+        // The endpoint has no date bounds, so filter the fetched notifications
+        let mut notifications = output.data.notifications;
+        notifications.retain(|notification| {
+            let indexed_at = notification.indexed_at.as_ref();
+            since
+                .as_ref()
+                .is_none_or(|since| indexed_at >= since.as_ref())
+                && until
+                    .as_ref()
+                    .is_none_or(|until| indexed_at < until.as_ref())
+        });
+        Ok(notifications)
+    }
+    #[tool(
+        description = "Get an image blob by its CID from an account's repo, so that its content can be seen."
+    )]
+    async fn get_blob(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The DID of the account.")]
+        did: String,
+        #[tool(param)]
+        #[schemars(description = "The CID of the blob to fetch.")]
+        cid: String,
+    ) -> Result<CallToolResult, Error> {
+        let did = did
+            .parse()
+            .map_err(map_invalid_params("failed to parse did"))?;
+        let cid = cid
+            .parse::<Cid>()
+            .map_err(map_invalid_params("failed to parse cid"))?;
+        let data = self
+            .agent
+            .api
+            .com
+            .atproto
+            .sync
+            .get_blob(atproto::sync::get_blob::ParametersData { cid, did }.into())
+            .await
+            .map_err(map_xrpc_err("failed to get blob"))?;
+        if data.len() > self.config.max_blob_size {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "blob is too large ({} bytes, max {} bytes)",
+                data.len(),
+                self.config.max_blob_size
+            ))]));
+        }
+        let Some(mime_type) = detect_image_mime_type(&data) else {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "blob is not a supported image ({} bytes)",
+                data.len()
+            ))]));
+        };
+        Ok(CallToolResult::success(vec![Content::image(
+            BASE64_STANDARD.encode(&data),
+            mime_type,
+        )]))
+    }
+    #[tool(description = "Get the current commit CID and revision of an account's repo.")]
+    async fn get_latest_commit(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The DID of the repo.")]
+        did: String,
+    ) -> Result<CallToolResult, Error> {
+        let did = did
+            .parse()
+            .map_err(map_invalid_params("failed to parse did"))?;
+        let output = self
+            .agent
+            .api
+            .com
+            .atproto
+            .sync
+            .get_latest_commit(atproto::sync::get_latest_commit::ParametersData { did }.into())
+            .await
+            .map_err(map_xrpc_err("failed to get latest commit"))?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "cid": output.data.cid,
+            "rev": output.data.rev,
+        }))?]))
+    }
+    #[tool(
+        description = "Upload an image blob to the current account's repo, returning a blob ref that can be referenced in records."
+    )]
+    async fn upload_blob(
+        &self,
+        #[tool(aggr)] params: UploadBlobParams,
+    ) -> Result<CallToolResult, Error> {
+        let blob = self.upload_image(params.path, params.data).await?;
+        Ok(CallToolResult::success(vec![self.json_content(blob)?]))
+    }
+    /// Builds an external link card embed from the OpenGraph tags of a web page, falling back
+    /// to the URL as the title without a thumbnail.
+    async fn external_embed(&self, url: &str) -> Result<bsky::embed::external::Main, Error> {
+        let page =
+            reqwest::Url::parse(url).map_err(map_invalid_params("failed to parse external url"))?;
+        let card = fetch_link_card(&self.http, page.as_str())
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("failed to fetch link card of {url}: {e:#}");
+                LinkCard::default()
+            });
+        let mut thumb = None;
+        if let Some(image) = card.image.and_then(|image| page.join(&image).ok()) {
+            match fetch_bytes(&self.http, image.as_str()).await {
+                Ok(data) => match self.upload_image_data(data).await {
+                    Ok(blob) => thumb = Some(blob),
+                    Err(e) => tracing::warn!("failed to upload thumbnail {image}: {}", e.message),
+                },
+                Err(e) => tracing::warn!("failed to fetch thumbnail {image}: {e:#}"),
+            }
+        }
+        Ok(bsky::embed::external::MainData {
+            external: bsky::embed::external::ExternalData {
+                description: card.description.unwrap_or_default(),
+                thumb,
+                title: card.title.unwrap_or_else(|| url.into()),
+                uri: url.into(),
+            }
+            .into(),
+        }
+        .into())
+    }
+    /// Reads an image from a local file or base64 data and uploads it as a blob.
+    async fn upload_image(
+        &self,
+        path: Option<String>,
+        data: Option<String>,
+    ) -> Result<BlobRef, Error> {
+        let data = match (path, data) {
+            (Some(path), None) => tokio::fs::read(&path)
+                .await
+                .map_err(map_invalid_params("failed to read file"))?,
+            (None, Some(data)) => BASE64_STANDARD
+                .decode(data.trim())
+                .map_err(map_invalid_params("failed to decode base64 data"))?,
+            _ => {
+                return Err(Error::invalid_params(
+                    "exactly one of path or data is required",
+                    None,
+                ));
+            }
+        };
+        self.upload_image_data(data).await
+    }
+    /// Uploads image data as a blob, rejecting data that is too large or not an image.
+    async fn upload_image_data(&self, data: Vec<u8>) -> Result<BlobRef, Error> {
+        if data.len() > self.config.max_blob_size {
+            return Err(Error::invalid_params(
+                format!(
+                    "blob is too large ({} bytes, max {} bytes)",
+                    data.len(),
+                    self.config.max_blob_size
+                ),
+                None,
+            ));
+        }
+        if detect_image_mime_type(&data).is_none() {
+            return Err(Error::invalid_params(
+                "blob is not a supported image (jpeg, png, gif, or webp)",
+                None,
+            ));
+        }
+        let output = self
+            .agent
+            .api
+            .com
+            .atproto
+            .repo
+            .upload_blob(data)
+            .await
+            .map_err(map_xrpc_err("failed to upload blob"))?;
+        Ok(output.data.blob)
+    }
+    #[tool(
+        description = "Write a record in the current account's repo, creating or updating it as needed."
+    )]
+    async fn put_record(
+        &self,
+        #[tool(aggr)] params: PutRecordParams,
+    ) -> Result<CallToolResult, Error> {
+        let repo = self.writable_repo(params.repo.as_deref()).await?;
+        let Value::Object(mut record) = params.record else {
+            return Err(Error::invalid_params("record must be a JSON object", None));
+        };
+        record
+            .entry("$type")
+            .or_insert_with(|| Value::String(params.collection.clone()));
+        let record = serde_json::from_value::<Unknown>(Value::Object(record))
+            .map_err(map_invalid_params("failed to parse record"))?;
+        let collection = params
+            .collection
+            .parse()
+            .map_err(map_invalid_params("failed to parse collection"))?;
+        let rkey = params
+            .rkey
+            .parse()
+            .map_err(map_invalid_params("failed to parse rkey"))?;
+        let output = self
             .agent
-            .did()
-            .await
-            .ok_or(Error::internal_error("failed to get did", None))?;
-        // Collect the uris of posts that have been replied from the current user
-        let mut replied = HashSet::new();
-        for handle in handles {
-            // Wait for the task to finish and get the result
-            let output = handle
-                .await
-                .map_err(|e| {
-                    Error::internal_error(
-                        "failed to await task",
-                        Some(Value::String(e.to_string())),
-                    )
-                })?
-                .map_err(|e| {
-                    Error::internal_error(
-                        "failed to get post thread",
-                        Some(Value::String(e.to_string())),
-                    )
-                })?;
-            // Check if the thread contains a reply from the user
-            if let Union::Refs(
-                bsky::feed::get_post_thread::OutputThreadRefs::AppBskyFeedDefsThreadViewPost(
-                    thread_view_post,
-                ),
-            ) = &output.thread
-            {
-                if let Some(replies) = &thread_view_post.replies {
-                    if replies.iter().any(|reply| {
-                        if let Union::Refs(
-                            bsky::feed::defs::ThreadViewPostRepliesItem::ThreadViewPost(view_post),
-                        ) = reply
-                        {
-                            view_post.post.author.did == did
-                        } else {
-                            false
-                        }
-                    }) {
-                        replied.insert(thread_view_post.post.uri.clone());
-                    }
+            .api
+            .com
+            .atproto
+            .repo
+            .put_record(
+                atproto::repo::put_record::InputData {
+                    collection,
+                    record,
+                    repo,
+                    rkey,
+                    swap_commit: None,
+                    swap_record: None,
+                    validate: None,
                 }
-            }
-        }
-        // Filter the notifications to only include those that have not been replied to
-        Ok(CallToolResult::success(vec![Content::json(
-            convert_datetime(
-                notifications
-                    .iter()
-                    .filter(|notification| !replied.contains(&notification.uri))
-                    .collect::<Vec<_>>(),
+                .into(),
             )
-            .map_err(|e| {
-                Error::internal_error(
-                    "failed to convert datetime",
-                    Some(Value::String(e.to_string())),
-                )
-            })?,
-        )?]))
+            .await
+            .map_err(map_xrpc_err("failed to put record"))?;
+        Ok(CallToolResult::success(vec![self.json_content(output)?]))
     }
-    async fn _list_notifications(
+    #[tool(description = "Delete a record in the current account's repo.")]
+    async fn delete_record(
         &self,
-        params: ListNotificationsParams,
-    ) -> Result<Vec<bsky::notification::list_notifications::Notification>, Error> {
-        let limit = Some(
-            params
-                .limit
-                .unwrap_or(DEFAULT_LIMIT)
-                .try_into()
-                .map_err(|e| {
-                    Error::internal_error("failed to parse limit", Some(Value::String(e)))
-                })?,
-        );
+        #[tool(aggr)] params: DeleteRecordParams,
+    ) -> Result<CallToolResult, Error> {
+        let repo = self.writable_repo(params.repo.as_deref()).await?;
+        let collection = params
+            .collection
+            .parse()
+            .map_err(map_invalid_params("failed to parse collection"))?;
+        let rkey = params
+            .rkey
+            .parse()
+            .map_err(map_invalid_params("failed to parse rkey"))?;
         let output = self
             .agent
             .api
-            .app
-            .bsky
-            .notification
-            .list_notifications(
-                bsky::notification::list_notifications::ParametersData {
-                    cursor: None,
-                    limit,
-                    priority: None,
-                    reasons: Some(params.reasons.iter().map(|r| r.to_string()).collect()),
-                    seen_at: None,
+            .com
+            .atproto
+            .repo
+            .delete_record(
+                atproto::repo::delete_record::InputData {
+                    collection,
+                    repo,
+                    rkey,
+                    swap_commit: None,
+                    swap_record: None,
                 }
                 .into(),
             )
             .await
-            .map_err(|e| {
-                Error::internal_error(
-                    "failed to list notifications",
-                    Some(Value::String(e.to_string())),
-                )
-            })?;
-        Ok(output.data.notifications)
+            .map_err(map_xrpc_err("failed to delete record"))?;
+        Ok(CallToolResult::success(vec![self.json_content(output)?]))
     }
     #[tool(
-        description = "Create a regular or reply post. Use `text` for content. Set `reply` to a post URI if replying."
+        description = "Detect the facets (mentions, links, and tags) that `create_post` would generate for the text, without posting."
+    )]
+    async fn detect_facets(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Text content to detect facets in.")]
+        text: String,
+    ) -> Result<CallToolResult, Error> {
+        let rt = RichText::new_with_detect_facets(text)
+            .await
+            .map_err(map_internal_err("failed to create rich text"))?;
+        Ok(CallToolResult::success(vec![
+            self.json_content(rt.facets.unwrap_or_default())?,
+        ]))
+    }
+    #[tool(
+        description = "Create a regular or reply post. Use `text` for content. Set `reply` to a post URI if replying, and also `reply_cid`, `root_uri`, and `root_cid` if they are already known."
     )]
     async fn create_post(
         &self,
         #[tool(aggr)] params: CreatePostParams,
     ) -> Result<CallToolResult, Error> {
-        let rt = RichText::new_with_detect_facets(params.text)
+        let text = params.text.clone();
+        if !self.config.duplicate_post_window.is_zero() && !params.force.unwrap_or_default() {
+            let recent_posts = self.recent_posts.lock().unwrap_or_else(|e| e.into_inner());
+            if recent_posts.iter().any(|(created, recent)| {
+                *recent == text && created.elapsed() < self.config.duplicate_post_window
+            }) {
+                return Err(Error::invalid_params(
+                    "the same text was posted recently; set `force` to post it again",
+                    None,
+                ));
+            }
+        }
+        let mut params = params;
+        let images = params.images.take().unwrap_or_default();
+        if images.len() > MAX_IMAGES {
+            return Err(Error::invalid_params(
+                format!("at most {MAX_IMAGES} images can be attached"),
+                None,
+            ));
+        }
+        if params.external.is_some() && !images.is_empty() {
+            return Err(Error::invalid_params(
+                "external and images cannot be attached together",
+                None,
+            ));
+        }
+        if self.config.require_alt_text {
+            let missing = images
+                .iter()
+                .enumerate()
+                .filter(|(_, image)| image.alt.trim().is_empty())
+                .map(|(i, _)| (i + 1).to_string())
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                return Err(Error::invalid_params(
+                    format!(
+                        "alt text is required, but missing for image(s) {}",
+                        missing.join(", ")
+                    ),
+                    None,
+                ));
+            }
+        }
+        // Accept bsky.app URLs as well as AT URIs for the referenced records
+        for reference in [&mut params.reply, &mut params.root_uri, &mut params.quote]
+            .into_iter()
+            .flatten()
+        {
+            *reference = self.normalize_ref(reference).await?;
+        }
+        let mut rt = RichText::new_with_detect_facets(params.text)
             .await
-            .map_err(|e| {
-                Error::internal_error(
-                    "failed to create rich text",
-                    Some(Value::String(e.to_string())),
-                )
-            })?;
+            .map_err(map_internal_err("failed to create rich text"))?;
+        if params.shorten_links.unwrap_or_default() {
+            if let Some(facets) = rt.facets.as_mut() {
+                rt.text = shorten_links(&rt.text, facets);
+            }
+        }
+        if params.strict_mentions.unwrap_or_default() {
+            check_mentions_resolved(&rt.text, rt.facets.as_deref().unwrap_or_default())?;
+        }
+        let created_at = match &params.created_at {
+            Some(created_at) => {
+                let created_at = created_at
+                    .parse::<Datetime>()
+                    .map_err(map_invalid_params("failed to parse created_at"))?;
+                // Allow a little clock skew, but not timestamps far in the future
+                if *created_at.as_ref() > Utc::now() + TimeDelta::minutes(5) {
+                    return Err(Error::invalid_params(
+                        "created_at must not be in the future",
+                        None,
+                    ));
+                }
+                created_at
+            }
+            None => Datetime::now(),
+        };
+        // Explicit langs take precedence over the configured default language
+        let langs = match params.langs.filter(|langs| !langs.is_empty()) {
+            Some(langs) => Some(
+                langs
+                    .iter()
+                    .map(|lang| parse_language(lang))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(map_invalid_params("failed to parse langs"))?,
+            ),
+            None => self.config.default_lang.clone().map(|lang| vec![lang]),
+        };
         let reply = if let Some(reply) = &params.reply {
-            let output = get_post(&self.agent, reply).await.map_err(|e| {
-                Error::internal_error("failed to get post", Some(Value::String(e.to_string())))
-            })?;
-            let strong_ref =
-                atproto::repo::strong_ref::Main::from(atproto::repo::strong_ref::MainData {
-                    cid: output
-                        .data
-                        .cid
-                        .ok_or(Error::internal_error("failed to get cid", None))?,
-                    uri: output.data.uri,
-                });
-            let record =
-                bsky::feed::post::Record::try_from_unknown(output.data.value).map_err(|e| {
-                    Error::internal_error(
-                        "failed to convert record",
-                        Some(Value::String(e.to_string())),
+            // Reject URIs of other records (e.g. likes or profiles) before fetching anything
+            for uri in std::iter::once(reply).chain(&params.root_uri) {
+                check_reply_uri(uri)?;
+            }
+            if let (Some(reply_cid), Some(root_uri), Some(root_cid)) =
+                (&params.reply_cid, &params.root_uri, &params.root_cid)
+            {
+                // Both refs are already known, so the parent post does not need to be fetched
+                let parse_cid = |cid: &str| {
+                    cid.parse::<Cid>()
+                        .map_err(map_invalid_params("failed to parse cid"))
+                };
+                Some(
+                    bsky::feed::post::ReplyRefData {
+                        parent: strong_ref_from_uri(
+                            &self.agent,
+                            reply,
+                            Some(parse_cid(reply_cid)?),
+                        )
+                        .await
+                        .map_err(map_internal_err("failed to build parent ref"))?,
+                        root: strong_ref_from_uri(
+                            &self.agent,
+                            root_uri,
+                            Some(parse_cid(root_cid)?),
+                        )
+                        .await
+                        .map_err(map_internal_err("failed to build root ref"))?,
+                    }
+                    .into(),
+                )
+            } else {
+                let output = get_post(&self.agent, reply)
+                    .await
+                    .map_err(map_internal_err("failed to get post"))?;
+                let strong_ref = strong_ref_from_record(&output)
+                    .map_err(map_internal_err("failed to build parent ref"))?;
+                let record = bsky::feed::post::Record::try_from_unknown(output.data.value)
+                    .map_err(map_internal_err("failed to convert record"))?;
+                let root = match &record.reply {
+                    Some(reply) => select_reply_root(
+                        &strong_ref,
+                        &reply.root,
+                        get_post(&self.agent, &reply.root.uri).await,
+                    )
+                    .map_err(map_internal_err("failed to get root post"))?,
+                    None => strong_ref.clone(),
+                };
+                Some(
+                    bsky::feed::post::ReplyRefData {
+                        parent: strong_ref,
+                        root,
+                    }
+                    .into(),
+                )
+            }
+        } else {
+            None
+        };
+        let record: Option<bsky::embed::record::Main> = match &params.quote {
+            Some(quote) => {
+                let (_, collection, _) =
+                    parse_at_uri(quote).map_err(map_invalid_params("failed to parse quote uri"))?;
+                if !matches!(
+                    collection.as_str(),
+                    "app.bsky.feed.post" | "app.bsky.feed.generator" | "app.bsky.graph.list"
+                ) {
+                    return Err(Error::invalid_params(
+                        format!(
+                            "quote must be a post, feed generator, or list, but {quote} is a {} record",
+                            collection.as_str()
+                        ),
+                        None,
+                    ));
+                }
+                let record = strong_ref_from_uri(&self.agent, quote, None)
+                    .await
+                    .map_err(map_internal_err("failed to get quoted record"))?;
+                Some(bsky::embed::record::MainData { record }.into())
+            }
+            None => None,
+        };
+        let mut uploaded = Vec::with_capacity(images.len());
+        for (i, image) in images.into_iter().enumerate() {
+            let name = image.path.clone().unwrap_or_else(|| "base64 data".into());
+            let blob = self
+                .upload_image(image.path, image.data)
+                .await
+                .map_err(|e| {
+                    Error::new(
+                        e.code,
+                        format!("failed to upload image {} ({name}): {}", i + 1, e.message),
+                        e.data,
                     )
                 })?;
-            let root = if let Some(reply) = &record.reply {
-                reply.root.clone()
-            } else {
-                strong_ref.clone()
-            };
-            Some(
-                bsky::feed::post::ReplyRefData {
-                    parent: strong_ref,
-                    root,
+            uploaded.push(
+                bsky::embed::images::ImageData {
+                    alt: image.alt,
+                    aspect_ratio: None,
+                    image: blob,
                 }
                 .into(),
-            )
+            );
+        }
+        let external = match &params.external {
+            Some(url) => Some(self.external_embed(url).await?),
+            None => None,
+        };
+        // Images and an external link card are exclusive, so at most one media is present
+        let media = if !uploaded.is_empty() {
+            Some(Media::Images(
+                bsky::embed::images::MainData { images: uploaded }.into(),
+            ))
         } else {
-            None
+            external.map(Media::External)
+        };
+        let embed = match (record, media) {
+            (Some(record), Some(media)) => Some(Union::Refs(
+                bsky::feed::post::RecordEmbedRefs::AppBskyEmbedRecordWithMediaMain(Box::new(
+                    bsky::embed::record_with_media::MainData {
+                        media: Union::Refs(match media {
+                            Media::Images(images) => {
+                                bsky::embed::record_with_media::MainMediaRefs::AppBskyEmbedImagesMain(
+                                    Box::new(images),
+                                )
+                            }
+                            Media::External(external) => {
+                                bsky::embed::record_with_media::MainMediaRefs::AppBskyEmbedExternalMain(
+                                    Box::new(external),
+                                )
+                            }
+                        }),
+                        record,
+                    }
+                    .into(),
+                )),
+            )),
+            (Some(record), None) => Some(Union::Refs(
+                bsky::feed::post::RecordEmbedRefs::AppBskyEmbedRecordMain(Box::new(record)),
+            )),
+            (None, Some(Media::Images(images))) => Some(Union::Refs(
+                bsky::feed::post::RecordEmbedRefs::AppBskyEmbedImagesMain(Box::new(images)),
+            )),
+            (None, Some(Media::External(external))) => Some(Union::Refs(
+                bsky::feed::post::RecordEmbedRefs::AppBskyEmbedExternalMain(Box::new(external)),
+            )),
+            (None, None) => None,
         };
         let post = self
             .agent
             .create_record(bsky::feed::post::RecordData {
-                created_at: Datetime::now(),
-                embed: None,
+                created_at,
+                embed,
                 entities: None,
                 facets: rt.facets,
                 labels: None,
-                langs: None,
+                langs,
                 reply,
                 tags: None,
                 text: rt.text,
             })
             .await
-            .map_err(|e| {
-                Error::internal_error(
-                    "failed to create record",
-                    Some(Value::String(e.to_string())),
+            .map_err(map_internal_err("failed to create record"))?;
+        {
+            let mut recent_posts = self.recent_posts.lock().unwrap_or_else(|e| e.into_inner());
+            if recent_posts.len() >= RECENT_POST_TEXTS {
+                recent_posts.pop_front();
+            }
+            recent_posts.push_back((Instant::now(), text));
+        }
+        let mut value =
+            convert_datetime(&post).map_err(map_internal_err("failed to convert datetime"))?;
+        value["url"] = json!(post_web_url(&post.uri).ok());
+        if params.hydrate.unwrap_or_default() {
+            let post_view = self
+                .agent
+                .api
+                .app
+                .bsky
+                .feed
+                .get_posts(
+                    bsky::feed::get_posts::ParametersData {
+                        uris: vec![post.uri.clone()],
+                    }
+                    .into(),
                 )
+                .await
+                .map_err(map_xrpc_err("failed to get created post"))?
+                .data
+                .posts
+                .pop();
+            value["postView"] = convert_datetime(post_view)
+                .map_err(map_internal_err("failed to convert datetime"))?;
+        }
+        Ok(CallToolResult::success(vec![self.json_content(value)?]))
+    }
+    #[tool(description = "Create a post quoting another post with commentary.")]
+    async fn quote_post(
+        &self,
+        #[tool(aggr)] params: QuotePostParams,
+    ) -> Result<CallToolResult, Error> {
+        // Quotes are posts with a record embed, so share the validation and facet detection
+        self.create_post(CreatePostParams {
+            text: params.text,
+            quote: Some(params.uri),
+            ..Default::default()
+        })
+        .await
+    }
+    #[tool(
+        description = "Like a post, returning the URI and CID of the created like record. If the post is already liked, the URI of the existing like record is returned instead, unless `force` is set."
+    )]
+    async fn like(&self, #[tool(aggr)] params: LikeParams) -> Result<CallToolResult, Error> {
+        let (subject, viewer) = self
+            .post_subject(&params.uri, params.cid.as_deref(), "liked")
+            .await?;
+        if !params.force.unwrap_or_default() {
+            if let Some(like) = viewer.and_then(|viewer| viewer.like.clone()) {
+                return Ok(CallToolResult::success(vec![self.json_content(json!({
+                    "uri": like,
+                    "alreadyLiked": true,
+                }))?]));
+            }
+        }
+        let record = bsky::feed::like::RecordData {
+            created_at: Datetime::now(),
+            subject,
+            via: None,
+        };
+        let like = self
+            .agent
+            .create_record(record)
+            .await
+            .map_err(map_internal_err("failed to create record"))?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "uri": like.uri,
+            "cid": like.cid,
+        }))?]))
+    }
+    #[tool(description = "Remove a like, given either the liked post or the like record itself.")]
+    async fn unlike(&self, #[tool(aggr)] params: UnlikeParams) -> Result<CallToolResult, Error> {
+        let uri = self.normalize_ref(&params.uri).await?;
+        let (repo, collection, _) =
+            parse_at_uri(&uri).map_err(map_invalid_params("failed to parse uri"))?;
+        let like = match collection.as_str() {
+            "app.bsky.feed.like" => {
+                self.writable_repo(Some(repo.as_ref())).await?;
+                uri
+            }
+            "app.bsky.feed.post" => {
+                // The post view tells which like record of the current user refers to it
+                let post = self
+                    .agent
+                    .api
+                    .app
+                    .bsky
+                    .feed
+                    .get_posts(
+                        bsky::feed::get_posts::ParametersData {
+                            uris: vec![uri.clone()],
+                        }
+                        .into(),
+                    )
+                    .await
+                    .map_err(map_xrpc_err("failed to get post"))?
+                    .data
+                    .posts
+                    .pop()
+                    .ok_or(Error::invalid_params(format!("post {uri} not found"), None))?;
+                post.viewer
+                    .as_ref()
+                    .and_then(|viewer| viewer.like.clone())
+                    .ok_or(Error::invalid_params(
+                        format!("post {uri} is not liked by the current account"),
+                        None,
+                    ))?
+            }
+            collection => {
+                return Err(Error::invalid_params(
+                    format!("{uri} is a {collection} record, not a post or a like"),
+                    None,
+                ));
+            }
+        };
+        self.agent
+            .delete_record(&like)
+            .await
+            .map_err(map_internal_err("failed to delete record"))?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "deleted": like,
+        }))?]))
+    }
+    #[tool(
+        description = "Repost a post, returning the URI and CID of the created repost record. If the post is already reposted, the URI of the existing repost record is returned instead, unless `force` is set."
+    )]
+    async fn repost(&self, #[tool(aggr)] params: RepostParams) -> Result<CallToolResult, Error> {
+        let (subject, viewer) = self
+            .post_subject(&params.uri, params.cid.as_deref(), "reposted")
+            .await?;
+        if !params.force.unwrap_or_default() {
+            if let Some(repost) = viewer.and_then(|viewer| viewer.repost.clone()) {
+                return Ok(CallToolResult::success(vec![self.json_content(json!({
+                    "uri": repost,
+                    "alreadyReposted": true,
+                }))?]));
+            }
+        }
+        let record = bsky::feed::repost::RecordData {
+            created_at: Datetime::now(),
+            subject,
+            via: None,
+        };
+        let repost = self
+            .agent
+            .create_record(record)
+            .await
+            .map_err(map_internal_err("failed to create record"))?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "uri": repost.uri,
+            "cid": repost.cid,
+        }))?]))
+    }
+    #[tool(
+        description = "Delete a post of the current account, along with its threadgate and postgate records if present."
+    )]
+    async fn delete_post(
+        &self,
+        #[tool(aggr)] params: DeletePostParams,
+    ) -> Result<CallToolResult, Error> {
+        let uri = self.normalize_ref(&params.uri).await?;
+        let (repo, collection, rkey) =
+            parse_at_uri(&uri).map_err(map_invalid_params("failed to parse uri"))?;
+        if collection.as_str() != "app.bsky.feed.post" {
+            return Err(Error::invalid_params(format!("{uri} is not a post"), None));
+        }
+        let repo = self.writable_repo(Some(repo.as_ref())).await?;
+        // Report a missing post with the server's message, instead of silently deleting nothing
+        get_post(&self.agent, &uri)
+            .await
+            .map_err(map_invalid_params("failed to get post"))?;
+        // Gates of a post share its record key
+        let mut collections = vec!["app.bsky.feed.post"];
+        for gate in ["app.bsky.feed.threadgate", "app.bsky.feed.postgate"] {
+            let gate_collection = gate.parse().map_err(|e: &str| {
+                Error::internal_error("failed to parse collection", Some(Value::String(e.into())))
             })?;
-        Ok(CallToolResult::success(vec![Content::json(post)?]))
+            let result = self
+                .agent
+                .api
+                .com
+                .atproto
+                .repo
+                .get_record(
+                    atproto::repo::get_record::ParametersData {
+                        cid: None,
+                        collection: gate_collection,
+                        repo: repo.clone(),
+                        rkey: rkey.clone(),
+                    }
+                    .into(),
+                )
+                .await;
+            match result {
+                Ok(_) => collections.push(gate),
+                // The post has no such gate
+                Err(e) if is_record_not_found(&e) => {}
+                Err(e) => return Err(xrpc_error("failed to get gate record", e)),
+            }
+        }
+        let input = serde_json::from_value::<atproto::repo::apply_writes::InputData>(json!({
+            "repo": repo,
+            "writes": collections
+                .iter()
+                .map(|collection| {
+                    json!({
+                        "$type": "com.atproto.repo.applyWrites#delete",
+                        "collection": collection,
+                        "rkey": rkey,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        }))
+        .map_err(map_internal_err("failed to build writes"))?;
+        self.agent
+            .api
+            .com
+            .atproto
+            .repo
+            .apply_writes(input.into())
+            .await
+            .map_err(map_xrpc_err("failed to delete post"))?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "deleted": collections
+                .iter()
+                .map(|collection| format!("at://{}/{collection}/{}", repo.as_ref(), rkey.as_str()))
+                .collect::<Vec<_>>(),
+        }))?]))
+    }
+    #[tool(
+        description = "Block an account, returning the URI of the block record and the handle and display name of the blocked account."
+    )]
+    async fn block(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Handle or DID of the account to block.")]
+        actor: String,
+    ) -> Result<CallToolResult, Error> {
+        let profile = self.moderation_target(&actor).await?;
+        if let Some(uri) = profile
+            .viewer
+            .as_ref()
+            .and_then(|viewer| viewer.blocking.clone())
+        {
+            return Ok(CallToolResult::success(vec![self.json_content(json!({
+                "uri": uri,
+                "actor": Self::moderated_actor(&profile),
+                "alreadyBlocked": true,
+            }))?]));
+        }
+        let output = self
+            .agent
+            .create_record(bsky::graph::block::RecordData {
+                created_at: Datetime::now(),
+                subject: profile.did.clone(),
+            })
+            .await
+            .map_err(map_internal_err("failed to create record"))?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "uri": output.uri,
+            "actor": Self::moderated_actor(&profile),
+        }))?]))
+    }
+    #[tool(
+        description = "Unblock an account, deleting the block record found from the account's profile. Returns the URI of the deleted record and the handle and display name of the account."
+    )]
+    async fn unblock(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Handle or DID of the account to unblock.")]
+        actor: String,
+    ) -> Result<CallToolResult, Error> {
+        let profile = self.moderation_target(&actor).await?;
+        let uri = profile
+            .viewer
+            .as_ref()
+            .and_then(|viewer| viewer.blocking.clone())
+            .ok_or(Error::invalid_params(
+                format!("{} is not blocked", profile.handle.as_str()),
+                None,
+            ))?;
+        self.agent
+            .delete_record(&uri)
+            .await
+            .map_err(map_internal_err("failed to delete record"))?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "deleted": uri,
+            "actor": Self::moderated_actor(&profile),
+        }))?]))
+    }
+    #[tool(
+        description = "Mute an account. Unlike blocks, mutes are private and have no record URI, so the DID of the muted account is returned along with its handle and display name. Timed mutes are not supported by the API yet, so a `duration` results in a permanent mute, reported with `timedMuteApplied: false`."
+    )]
+    async fn mute(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Handle or DID of the account to mute.")]
+        actor: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Optional duration of the mute, such as `30m`, `24h`, `7d`, or `2w`."
+        )]
+        duration: Option<String>,
+    ) -> Result<CallToolResult, Error> {
+        let expiry = duration
+            .as_deref()
+            .map(parse_duration)
+            .transpose()
+            .map_err(map_invalid_params("failed to parse duration"))?
+            .map(|duration| Datetime::new((Utc::now() + duration).fixed_offset()));
+        let profile = self.moderation_target(&actor).await?;
+        self.agent
+            .api
+            .app
+            .bsky
+            .graph
+            .mute_actor(
+                bsky::graph::mute_actor::InputData {
+                    actor: AtIdentifier::Did(profile.did.clone()),
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to mute actor"))?;
+        let mut value = json!({
+            "success": true,
+            "muted": Self::moderated_actor(&profile),
+        });
+        // `app.bsky.graph.muteActor` has no expiry, so a timed mute falls back to a permanent one
+        let note = expiry.map(|expiry| {
+            value["timedMuteApplied"] = Value::Bool(false);
+            value["requestedExpiry"] = json!(expiry.as_str());
+            format!(
+                "timed mutes are not supported, so the account was muted permanently; unmute it after {} to honor the requested duration.",
+                expiry.as_str()
+            )
+        });
+        let mut contents = vec![self.json_content(value)?];
+        contents.extend(note.map(Content::text));
+        Ok(CallToolResult::success(contents))
+    }
+    #[tool(
+        description = "Unmute an account. Mutes have no record URI, so the DID of the unmuted account is returned along with its handle and display name."
+    )]
+    async fn unmute(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Handle or DID of the account to unmute.")]
+        actor: String,
+    ) -> Result<CallToolResult, Error> {
+        let profile = self.moderation_target(&actor).await?;
+        self.agent
+            .api
+            .app
+            .bsky
+            .graph
+            .unmute_actor(
+                bsky::graph::unmute_actor::InputData {
+                    actor: AtIdentifier::Did(profile.did.clone()),
+                }
+                .into(),
+            )
+            .await
+            .map_err(map_xrpc_err("failed to unmute actor"))?;
+        Ok(CallToolResult::success(vec![self.json_content(json!({
+            "success": true,
+            "unmuted": Self::moderated_actor(&profile),
+        }))?]))
+    }
+    #[tool(
+        description = "Report an account or a record (e.g. a post) for moderation, optionally to a specific labeler."
+    )]
+    async fn report(&self, #[tool(aggr)] params: ReportParams) -> Result<CallToolResult, Error> {
+        let subject = if params.subject.starts_with("at://") || params.subject.starts_with("http") {
+            let uri = self.normalize_ref(&params.subject).await?;
+            let output = get_post(&self.agent, &uri)
+                .await
+                .map_err(map_invalid_params("failed to get subject record"))?;
+            json!({
+                "$type": "com.atproto.repo.strongRef",
+                "uri": output.data.uri,
+                "cid": output.data.cid,
+            })
+        } else {
+            let did = params
+                .subject
+                .parse::<Did>()
+                .map_err(map_invalid_params("failed to parse subject"))?;
+            json!({
+                "$type": "com.atproto.admin.defs#repoRef",
+                "did": did,
+            })
+        };
+        let input =
+            serde_json::from_value::<atproto::moderation::create_report::InputData>(json!({
+                "reasonType": params.reason_type.to_string(),
+                "reason": params.reason,
+                "subject": subject,
+            }))
+            .map_err(map_internal_err("failed to build report"))?;
+        let output = match &params.labeler {
+            // Route the report to the labeler through the PDS
+            Some(labeler) => {
+                let labeler = labeler
+                    .parse::<Did>()
+                    .map_err(map_invalid_params("failed to parse labeler"))?;
+                self.agent
+                    .api_with_proxy(labeler, "atproto_labeler")
+                    .com
+                    .atproto
+                    .moderation
+                    .create_report(input.into())
+                    .await
+            }
+            None => {
+                self.agent
+                    .api
+                    .com
+                    .atproto
+                    .moderation
+                    .create_report(input.into())
+                    .await
+            }
+        }
+        .map_err(map_xrpc_err("failed to create report"))?;
+        Ok(CallToolResult::success(vec![
+            self.json_content(
+                convert_datetime(output.data)
+                    .map_err(map_internal_err("failed to convert datetime"))?,
+            )?,
+        ]))
     }
 }
 
-#[tool(tool_box)]
 impl ServerHandler for BskyService {
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, Error> {
+        if !self.config.is_tool_enabled(&request.name) {
+            return Err(Error::invalid_request(
+                format!("tool {} is disabled", request.name),
+                None,
+            ));
+        }
+        let name = request.name.clone();
+        let cache_key =
+            (self.cache.is_cacheable(&name) && !self.bypasses_cache(&request)).then(|| {
+                format!(
+                    "{name}:{}",
+                    serde_json::to_string(&request.arguments).unwrap_or_default()
+                )
+            });
+        if let Some(result) = cache_key.as_deref().and_then(|key| self.cache.get(key)) {
+            #[cfg(feature = "metrics")]
+            self.metrics.record(&name, false);
+            return Ok(result);
+        }
+        let result = tokio::time::timeout(
+            self.config.tool_timeout,
+            Self::tool_box().call(ToolCallContext::new(self, request, context)),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            Err(Error::new(
+                TIMEOUT_ERROR_CODE,
+                format!(
+                    "tool {name} timed out after {} seconds",
+                    self.config.tool_timeout.as_secs()
+                ),
+                None,
+            ))
+        });
+        // Tell when the rate limit resets, from the headers of the rejected response
+        let result = result.map_err(|mut e| {
+            if e.code == RATE_LIMIT_ERROR_CODE {
+                let rate_limit = self
+                    .rate_limit
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .clone();
+                if let Some(Value::Object(data)) = &mut e.data {
+                    data.insert("ratelimitRemaining".into(), json!(rate_limit.remaining));
+                    data.insert("ratelimitReset".into(), json!(rate_limit.reset));
+                }
+            }
+            e
+        });
+        match (&cache_key, &result) {
+            (Some(key), Ok(result)) if !result.is_error.unwrap_or_default() => {
+                self.cache.insert(key.clone(), result.clone());
+            }
+            // Written data (or a switched account) invalidates cached reads
+            (None, _) if self.cache.is_invalidated_by(&name) => self.cache.clear(),
+            _ => {}
+        }
+        #[cfg(feature = "metrics")]
+        self.metrics.record(
+            &name,
+            result
+                .as_ref()
+                .map(|result| result.is_error.unwrap_or_default())
+                .unwrap_or(true),
+        );
+        result
+    }
+    async fn list_tools(
+        &self,
+        _: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, Error> {
+        Ok(ListToolsResult {
+            next_cursor: None,
+            tools: Self::tool_box()
+                .list()
+                .into_iter()
+                .filter(|tool| self.config.is_tool_enabled(&tool.name))
+                .collect(),
+        })
+    }
     async fn get_prompt(
         &self,
         request: GetPromptRequestParam,
         _: RequestContext<RoleServer>,
     ) -> Result<GetPromptResult, Error> {
-        match request.name.as_ref() {
-            "get_self_feed" => Ok(GetPromptResult {
-                description: None,
-                messages: vec![
-                    PromptMessage::new_text(
-                        PromptMessageRole::User,
-                        "I want to view my own recent posts.",
-                    ),
-                    PromptMessage::new_text(
-                        PromptMessageRole::Assistant,
-                        "First, call the `get_did` tool to retrieve the current user's DID. Then, call the `get_author_feed` tool using that DID as the `actor` parameter. If the user specifies a number of posts to retrieve (e.g., 'last 3 posts'), include that as the `limit` parameter. Otherwise, omit `limit`.",
-                    ),
-                ],
-            }),
-            _ => Err(Error::invalid_params("prompt not found", None)),
-        }
+        let arguments = request.arguments.unwrap_or_default();
+        let argument = |name: &str| {
+            arguments
+                .get(name)
+                .map(|value| value.as_str().map(str::trim).unwrap_or_default())
+                .filter(|value| !value.is_empty())
+        };
+        let (user, assistant) = match request.name.as_ref() {
+            "get_self_feed" => match argument("count") {
+                Some(count) => {
+                    count
+                        .parse::<u8>()
+                        .map_err(map_invalid_params("failed to parse count"))?;
+                    (
+                        "I want to view my last {count} posts.",
+                        "Call the `get_author_feed` tool with `me` as the `actor` parameter, with `limit` set to {count}.",
+                    )
+                }
+                None => (
+                    "I want to view my own recent posts.",
+                    "Call the `get_author_feed` tool with `me` as the `actor` parameter. If the user specifies a number of posts to retrieve (e.g., 'last 3 posts'), include that as the `limit` parameter. Otherwise, omit `limit`.",
+                ),
+            },
+            "search_and_summarize" => match argument("query") {
+                Some(_) => (
+                    "I want a summary of what people are posting about {query}.",
+                    "First, call the `search_posts` tool with `{query}` as the `q` parameter, with `limit` set to 100. If the results are too few to summarize, gather more by calling `search_posts` again with `cursor` set to the `cursor` of the previous results, up to a few times or until no `cursor` is returned. Then, write a concise summary of the posts grouped by theme, noting for each theme how many posts discuss it and quoting a representative post with its author's handle.",
+                ),
+                None => (
+                    "I want a summary of what people are posting about a topic.",
+                    "First, call the `search_posts` tool with the user's topic as the `q` parameter, with `limit` set to 100. If the results are too few to summarize, gather more by calling `search_posts` again with `cursor` set to the `cursor` of the previous results, up to a few times or until no `cursor` is returned. Then, write a concise summary of the posts grouped by theme, noting for each theme how many posts discuss it and quoting a representative post with its author's handle.",
+                ),
+            },
+            _ => return Err(Error::invalid_params("prompt not found", None)),
+        };
+        Ok(GetPromptResult {
+            description: None,
+            messages: vec![
+                PromptMessage::new_text(PromptMessageRole::User, fill_prompt(user, &arguments)),
+                PromptMessage::new_text(
+                    PromptMessageRole::Assistant,
+                    fill_prompt(assistant, &arguments),
+                ),
+            ],
+        })
     }
     async fn list_prompts(
         &self,
@@ -494,11 +3342,26 @@ impl ServerHandler for BskyService {
     ) -> Result<ListPromptsResult, Error> {
         Ok(ListPromptsResult {
             next_cursor: None,
-            prompts: vec![Prompt::new(
-                "get_self_feed",
-                Some("Get the self feed of the current user"),
-                None,
-            )],
+            prompts: vec![
+                Prompt::new(
+                    "get_self_feed",
+                    Some("Get the self feed of the current user"),
+                    Some(vec![PromptArgument {
+                        name: "count".into(),
+                        description: Some("Number of recent posts to retrieve".into()),
+                        required: Some(false),
+                    }]),
+                ),
+                Prompt::new(
+                    "search_and_summarize",
+                    Some("Search posts about a topic and summarize them by theme"),
+                    Some(vec![PromptArgument {
+                        name: "query".into(),
+                        description: Some("Topic or search query".into()),
+                        required: Some(false),
+                    }]),
+                ),
+            ],
         })
     }
     fn get_info(&self) -> ServerInfo {
@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use rmcp::serde_json;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{collections::HashMap, fs, io, path::PathBuf, sync::Mutex};
+
+/// Follower and following counts of an account observed by `get_profile`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountObservation {
+    pub followers_count: i64,
+    pub follows_count: i64,
+    pub observed_at: String,
+}
+
+/// Most recent post seen in a feed, marking where the next check of the feed starts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedMarker {
+    pub uri: String,
+    pub indexed_at: String,
+}
+
+/// Local JSON file store of the last recorded entries, keyed by a string such as a DID.
+#[derive(Debug)]
+pub struct FileStore<T> {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, T>>,
+}
+
+impl<T> FileStore<T>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Opens the store at `path`, which is empty if the file does not exist yet.
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .with_context(|| format!("failed to parse {}", path.display()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+        Ok(Self {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+    /// Returns the entry of the key, if any.
+    pub fn get(&self, key: &str) -> Option<T> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(key)
+            .cloned()
+    }
+    /// Records the entry of the key and writes the store to the file, returning the
+    /// previous entry if any.
+    pub fn record(&self, key: &str, entry: T) -> Result<Option<T>> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = entries.insert(key.into(), entry);
+        fs::write(&self.path, serde_json::to_string(&*entries)?)
+            .with_context(|| format!("failed to write {}", self.path.display()))?;
+        Ok(previous)
+    }
+}
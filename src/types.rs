@@ -1,29 +1,229 @@
-use rmcp::schemars::{self, JsonSchema};
+use rmcp::{
+    schemars::{self, JsonSchema},
+    serde_json::Value,
+};
 use serde::Deserialize;
 use std::fmt;
 
 pub const DEFAULT_LIMIT: u8 = 10;
 pub const DEFAULT_DEPTH: u16 = 1;
 pub const DEFAULT_PARENT_HEIGHT: u16 = 10;
+pub const MAX_DEPTH: u16 = 1000;
+pub const MAX_PARENT_HEIGHT: u16 = 1000;
+pub const MAX_PROFILES: usize = 25;
+pub const DEFAULT_EMBED_DEPTH: u8 = 1;
+pub const MAX_EMBED_DEPTH: u8 = 5;
+pub const MAX_AUTHOR_FEED_PAGES: usize = 10;
+pub const MAX_GRAPH_PAGES: usize = 20;
+pub const MAX_IMAGES: usize = 4;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetProfilesParams {
+    #[schemars(description = "Handles or DIDs of accounts to fetch profiles of. Max is 25.")]
+    pub actors: Vec<String>,
+    #[schemars(
+        description = "Whether to return compact summaries with the viewer's relationship (following, followedBy, blocking, muted) instead of full profiles."
+    )]
+    pub relationships: Option<bool>,
+}
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct GetAuthorFeedParams {
-    #[schemars(description = "Handle or DID of account to fetch author feed of.")]
+    #[schemars(
+        description = "Handle or DID of account to fetch author feed of, or `me` for the current account."
+    )]
     pub actor: String,
     #[schemars(description = "Limit for the number of posts to fetch.")]
     pub limit: Option<u8>,
     #[schemars(description = "Whether to include replies in the feed.")]
     pub with_replies: Option<bool>,
+    #[schemars(description = "Whether to include the pinned post at the top of the feed.")]
+    pub include_pins: Option<bool>,
+    #[schemars(
+        description = "Only include posts after the indicated datetime (inclusive), paging through the feed as needed (up to 10 pages). Accepts ISO 8601, or relative forms such as `24h`, `7d`, or `yesterday`."
+    )]
+    pub since: Option<String>,
+    #[schemars(
+        description = "Only include posts before the indicated datetime (not inclusive), paging through the feed as needed (up to 10 pages). Accepts ISO 8601, or relative forms such as `24h`, `7d`, or `yesterday`."
+    )]
+    pub until: Option<String>,
+    #[schemars(
+        description = "How to handle reposts: `include` them as is (default), `exclude` them, or `tag` each item with an `isRepost` flag."
+    )]
+    pub reposts: Option<RepostsEnum>,
+    #[schemars(
+        description = "How many levels of quoted posts to inline as `quotedPost` when they are only referenced. Max is 5."
+    )]
+    pub embed_depth: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepostsEnum {
+    Include,
+    Exclude,
+    Tag,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetTimelineParams {
+    #[schemars(description = "Limit for the number of posts to fetch.")]
+    pub limit: Option<u8>,
+    #[schemars(description = "Cursor for pagination, returned from a previous call.")]
+    pub cursor: Option<String>,
+    #[schemars(
+        description = "Whether to remove duplicate posts (e.g. reposted by multiple follows), keeping the first occurrence."
+    )]
+    pub dedup: Option<bool>,
+    #[schemars(
+        description = "Whether to request the strictly reverse-chronological timeline of followed accounts (`reverse-chronological` algorithm of `app.bsky.feed.getTimeline`)."
+    )]
+    pub following_only: Option<bool>,
+    #[schemars(
+        description = "How many levels of quoted posts to inline as `quotedPost` when they are only referenced. Max is 5."
+    )]
+    pub embed_depth: Option<u8>,
+    #[schemars(
+        description = "Whether to mark posts that are new since the previous call with this option as `isNew`, counting them as `newCount`. Requires `BSKY_FEED_MARKER_STORE`."
+    )]
+    pub mark_new: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetFeedGeneratorsParams {
+    #[schemars(description = "References (AT-URIs or bsky.app URLs) to feed generator records.")]
+    pub feeds: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetFeedParams {
+    #[schemars(description = "Reference (AT-URI or bsky.app URL) to the feed generator record.")]
+    pub feed: String,
+    #[schemars(description = "Limit for the number of posts to fetch.")]
+    pub limit: Option<u8>,
+    #[schemars(description = "Cursor for pagination, returned from a previous call.")]
+    pub cursor: Option<String>,
+    #[schemars(
+        description = "Whether to first check that the feed generator is online and valid, to report a clear message instead of an error. Defaults to true."
+    )]
+    pub check_generator: Option<bool>,
+    #[schemars(
+        description = "Whether to mark posts that are new since the previous call with this option as `isNew`, counting them as `newCount`. Requires `BSKY_FEED_MARKER_STORE`."
+    )]
+    pub mark_new: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetListFeedParams {
+    #[schemars(description = "Reference (AT-URI or bsky.app URL) to the list.")]
+    pub list: String,
+    #[schemars(description = "Limit for the number of posts to fetch.")]
+    pub limit: Option<u8>,
+    #[schemars(description = "Cursor for pagination, returned from a previous call.")]
+    pub cursor: Option<String>,
+    #[schemars(
+        description = "Whether to exclude reposts, returning only posts written by list members. Reposts are included by default."
+    )]
+    pub exclude_reposts: Option<bool>,
+    #[schemars(
+        description = "Whether to mark posts that are new since the previous call with this option as `isNew`, counting them as `newCount`. Requires `BSKY_FEED_MARKER_STORE`."
+    )]
+    pub mark_new: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SendInteractionsParams {
+    #[schemars(
+        description = "Reference (AT-URI or bsky.app URL) to the feed generator whose posts were interacted with."
+    )]
+    pub feed: String,
+    #[schemars(
+        description = "Feed context token of the feed items, returned as `feedContext` by `get_feed`."
+    )]
+    pub feed_context: Option<String>,
+    #[schemars(description = "Interactions with posts from the feed.")]
+    pub interactions: Vec<InteractionParams>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct InteractionParams {
+    #[schemars(description = "Reference (AT-URI) to the post interacted with.")]
+    pub uri: String,
+    #[schemars(description = "The kind of interaction.")]
+    pub event: InteractionEventEnum,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum InteractionEventEnum {
+    Seen,
+    Clicked,
+    Liked,
+    Reposted,
+    Replied,
+    Quoted,
+    Shared,
+    RequestMore,
+    RequestLess,
+}
+
+impl fmt::Display for InteractionEventEnum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let event = match self {
+            InteractionEventEnum::Seen => "app.bsky.feed.defs#interactionSeen",
+            InteractionEventEnum::Clicked => "app.bsky.feed.defs#clickthroughItem",
+            InteractionEventEnum::Liked => "app.bsky.feed.defs#interactionLike",
+            InteractionEventEnum::Reposted => "app.bsky.feed.defs#interactionRepost",
+            InteractionEventEnum::Replied => "app.bsky.feed.defs#interactionReply",
+            InteractionEventEnum::Quoted => "app.bsky.feed.defs#interactionQuote",
+            InteractionEventEnum::Shared => "app.bsky.feed.defs#interactionShare",
+            InteractionEventEnum::RequestMore => "app.bsky.feed.defs#requestMore",
+            InteractionEventEnum::RequestLess => "app.bsky.feed.defs#requestLess",
+        };
+        write!(f, "{event}")
+    }
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct GetPostThreadParams {
-    #[schemars(description = "Reference (AT-URI) to post record.")]
+    #[schemars(description = "Reference (AT-URI or bsky.app URL) to post record.")]
     pub uri: String,
-    #[schemars(description = "How many levels of reply depth should be included in response.")]
+    #[schemars(
+        description = "How many levels of reply depth should be included in response. Max is 1000."
+    )]
     pub depth: Option<u16>,
-    #[schemars(description = "How many levels of parent (and grandparent, etc) post to include.")]
+    #[schemars(
+        description = "How many levels of parent (and grandparent, etc) post to include. Max is 1000."
+    )]
     pub parent_height: Option<u16>,
+    #[schemars(
+        description = "Number of levels of both parents and replies to include around the post. Explicit `depth` and `parent_height` take precedence."
+    )]
+    pub context: Option<u16>,
+    #[schemars(
+        description = "Whether to mark each post with `repliedByMe`, whether the current user has replied directly to it among the fetched replies."
+    )]
+    pub replied_by_me: Option<bool>,
+    #[schemars(
+        description = "Maximum number of direct replies to keep for each post, preferring the most liked ones. Posts with removed replies are marked with their count as `more`."
+    )]
+    pub max_replies_per_node: Option<usize>,
+    #[schemars(
+        description = "Output format: `json` for the thread structure (default), or `transcript` for `@handle: text (timestamp)` lines."
+    )]
+    pub format: Option<ThreadFormatEnum>,
+    #[schemars(
+        description = "How many levels of quoted posts to inline as `quotedPost` when they are only referenced. Max is 5."
+    )]
+    pub embed_depth: Option<u8>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThreadFormatEnum {
+    #[default]
+    Json,
+    Transcript,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -34,6 +234,39 @@ pub struct SearchPostsParams {
     pub q: String,
     #[schemars(description = "Limit for the number of posts to fetch.")]
     pub limit: Option<u8>,
+    #[schemars(description = "Cursor for pagination, returned from a previous call.")]
+    pub cursor: Option<String>,
+    #[schemars(
+        description = "Filter results for posts after the indicated datetime (inclusive). Accepts ISO 8601, or relative forms such as `24h`, `7d`, or `yesterday`."
+    )]
+    pub since: Option<String>,
+    #[schemars(
+        description = "Filter results for posts before the indicated datetime (not inclusive). Accepts ISO 8601, or relative forms such as `24h`, `7d`, or `yesterday`."
+    )]
+    pub until: Option<String>,
+    #[schemars(
+        description = "How to re-rank the fetched posts: `server` order (default), by `likes`, `reposts`, or `replies` count, or by `engagement`, a weighted combination of the counts that decays with age."
+    )]
+    pub rank_by: Option<RankByEnum>,
+    #[schemars(
+        description = "Whether to add a short summary of the parent post to each reply as `parentContext`."
+    )]
+    pub include_parent: Option<bool>,
+    #[schemars(
+        description = "How many levels of quoted posts to inline as `quotedPost` when they are only referenced. Max is 5."
+    )]
+    pub embed_depth: Option<u8>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RankByEnum {
+    #[default]
+    Server,
+    Likes,
+    Reposts,
+    Replies,
+    Engagement,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -67,21 +300,319 @@ impl fmt::Display for ReasonEnum {
     }
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetActorStarterPacksParams {
+    #[schemars(
+        description = "Handle or DID of account to fetch starter packs of, or `me` for the current account."
+    )]
+    pub actor: String,
+    #[schemars(description = "Limit for the number of starter packs to fetch.")]
+    pub limit: Option<u8>,
+    #[schemars(description = "Cursor for pagination, returned from a previous call.")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetFollowersParams {
+    #[schemars(
+        description = "Handle or DID of account to fetch followers of, or `me` for the current account."
+    )]
+    pub actor: String,
+    #[schemars(description = "Limit for the number of followers to fetch.")]
+    pub limit: Option<u8>,
+    #[schemars(description = "Cursor for pagination, returned from a previous call.")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetFollowsParams {
+    #[schemars(
+        description = "Handle or DID of account to fetch follows of, or `me` for the current account."
+    )]
+    pub actor: String,
+    #[schemars(description = "Limit for the number of follows to fetch.")]
+    pub limit: Option<u8>,
+    #[schemars(description = "Cursor for pagination, returned from a previous call.")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FollowAllParams {
+    #[schemars(description = "Handles or DIDs of accounts to follow.")]
+    pub actors: Option<Vec<String>>,
+    #[schemars(
+        description = "Reference (AT-URI or bsky.app URL) to a list whose members to follow, in addition to `actors`."
+    )]
+    pub list: Option<String>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ListNotificationsParams {
     #[schemars(description = "Limit for the number of notifications to fetch.")]
     pub limit: Option<u8>,
-    #[schemars(description = "Notification reasons to include in response.")]
+    #[schemars(
+        description = "Notification reasons to include in response. Empty or omitted means the server default, which is all reasons unless configured otherwise."
+    )]
+    #[serde(default)]
     pub reasons: Vec<ReasonEnum>,
+    #[schemars(
+        description = "Whether to only include priority notifications, such as those from accounts the user follows."
+    )]
+    pub priority: Option<bool>,
+    #[schemars(
+        description = "Only include fetched notifications indexed after the indicated datetime (inclusive). Accepts ISO 8601, or relative forms such as `24h`, `7d`, or `yesterday`."
+    )]
+    pub since: Option<String>,
+    #[schemars(
+        description = "Only include fetched notifications indexed before the indicated datetime (not inclusive). Accepts ISO 8601, or relative forms such as `24h`, `7d`, or `yesterday`."
+    )]
+    pub until: Option<String>,
+    #[schemars(
+        description = "Datetime to compute the `isRead` flags against, instead of the stored last seen time. Accepts ISO 8601, or relative forms such as `24h`, `7d`, or `yesterday`."
+    )]
+    pub seen_at: Option<String>,
 }
 
-#[derive(Debug, Deserialize, JsonSchema)]
+#[derive(Debug, Default, Deserialize, JsonSchema)]
 pub struct CreatePostParams {
     #[schemars(
         description = "Text content of the post. Max length is 300 characters.",
         length(max = 300)
     )]
     pub text: String,
-    #[schemars(description = "Optional URI of the post being replied to.")]
+    #[schemars(
+        description = "Optional language codes of the post text (e.g. `en`, `ja`). If omitted, the server's default language is used if configured."
+    )]
+    pub langs: Option<Vec<String>>,
+    #[schemars(
+        description = "Optional URI of the post being replied to, as an AT-URI or a bsky.app URL."
+    )]
     pub reply: Option<String>,
+    #[schemars(
+        description = "Optional CID of the post being replied to. If set together with `root_uri` and `root_cid`, the reply is built without fetching the post."
+    )]
+    pub reply_cid: Option<String>,
+    #[schemars(description = "Optional URI of the root post of the thread being replied to.")]
+    pub root_uri: Option<String>,
+    #[schemars(description = "Optional CID of the root post of the thread being replied to.")]
+    pub root_cid: Option<String>,
+    #[schemars(
+        description = "Optional creation datetime of the post in ISO 8601 format, for importing content with its original timestamp. Defaults to now."
+    )]
+    pub created_at: Option<String>,
+    #[schemars(
+        description = "Whether to fail instead of posting if any `@handle` mention cannot be resolved, since unresolved mentions are posted as plain text and notify no one."
+    )]
+    pub strict_mentions: Option<bool>,
+    #[schemars(
+        description = "Whether to display links in shortened form (e.g. `example.com/very-long-pa...`), keeping the full URLs as link targets."
+    )]
+    pub shorten_links: Option<bool>,
+    #[schemars(
+        description = "Optional URI of a post, feed generator, or list to embed as a quote, as an AT-URI or a bsky.app URL."
+    )]
+    pub quote: Option<String>,
+    #[schemars(
+        description = "Whether to post even if the same text was posted recently, which is rejected by default as an accidental double post."
+    )]
+    pub force: Option<bool>,
+    #[schemars(
+        description = "Whether to also return the view of the created post as `postView`, which takes an extra request."
+    )]
+    pub hydrate: Option<bool>,
+    #[schemars(
+        description = "Optional URL of a web page to attach as a link card, built from its OpenGraph tags. Cannot be used with `images`."
+    )]
+    pub external: Option<String>,
+    #[schemars(description = "Optional images to attach to the post. Max is 4.")]
+    pub images: Option<Vec<ImageParams>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImageParams {
+    #[schemars(description = "Path to a local image file to attach.")]
+    pub path: Option<String>,
+    #[schemars(
+        description = "Base64-encoded image data to attach. Exactly one of `path` or `data` is required."
+    )]
+    pub data: Option<String>,
+    #[schemars(description = "Alt text describing the image, for accessibility.")]
+    #[serde(default)]
+    pub alt: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct QuotePostParams {
+    #[schemars(
+        description = "Text content of the quote post. Max length is 300 characters.",
+        length(max = 300)
+    )]
+    pub text: String,
+    #[schemars(description = "Reference (AT-URI or bsky.app URL) to the post to quote.")]
+    pub uri: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LikeParams {
+    #[schemars(description = "Reference (AT-URI or bsky.app URL) to the post to like.")]
+    pub uri: String,
+    #[schemars(description = "CID of the post to like. If omitted, it is resolved from the post.")]
+    pub cid: Option<String>,
+    #[schemars(
+        description = "Whether to create a new like record even if the post is already liked by the current account. By default, the existing like record is returned instead."
+    )]
+    pub force: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UnlikeParams {
+    #[schemars(
+        description = "Reference (AT-URI or bsky.app URL) to the liked post, or the AT-URI of the like record."
+    )]
+    pub uri: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RepostParams {
+    #[schemars(description = "Reference (AT-URI or bsky.app URL) to the post to repost.")]
+    pub uri: String,
+    #[schemars(
+        description = "CID of the post to repost. If omitted, it is resolved from the post."
+    )]
+    pub cid: Option<String>,
+    #[schemars(
+        description = "Whether to create a new repost record even if the post is already reposted by the current account. By default, the existing repost record is returned instead."
+    )]
+    pub force: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DeletePostParams {
+    #[schemars(
+        description = "Reference (AT-URI or bsky.app URL) to the post of the current account to delete."
+    )]
+    pub uri: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetListBlocksParams {
+    #[schemars(description = "Limit for the number of lists to fetch.")]
+    pub limit: Option<u8>,
+    #[schemars(description = "Cursor for pagination, returned from a previous call.")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetListMutesParams {
+    #[schemars(description = "Limit for the number of lists to fetch.")]
+    pub limit: Option<u8>,
+    #[schemars(description = "Cursor for pagination, returned from a previous call.")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetServiceAuthParams {
+    #[schemars(
+        description = "The DID of the service that the token will be used to authenticate with."
+    )]
+    pub aud: String,
+    #[schemars(description = "Lexicon (XRPC) method to bind the requested token to.")]
+    pub lxm: Option<String>,
+    #[schemars(description = "Number of seconds until the token expires.")]
+    pub expires_in: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PutRecordParams {
+    #[schemars(
+        description = "The handle or DID of the repo (aka, current account). Defaults to the current account."
+    )]
+    pub repo: Option<String>,
+    #[schemars(description = "The NSID of the record collection.")]
+    pub collection: String,
+    #[schemars(description = "The Record Key.")]
+    pub rkey: String,
+    #[schemars(description = "The record to write, as a JSON object.")]
+    pub record: Value,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DeleteRecordParams {
+    #[schemars(
+        description = "The handle or DID of the repo (aka, current account). Defaults to the current account."
+    )]
+    pub repo: Option<String>,
+    #[schemars(description = "The NSID of the record collection.")]
+    pub collection: String,
+    #[schemars(description = "The Record Key.")]
+    pub rkey: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UploadBlobParams {
+    #[schemars(description = "Path to a local image file to upload.")]
+    pub path: Option<String>,
+    #[schemars(
+        description = "Base64-encoded image data to upload. Exactly one of `path` or `data` is required."
+    )]
+    pub data: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReportParams {
+    #[schemars(
+        description = "The subject to report: an AT-URI or bsky.app URL of a record (e.g. a post), or the DID of an account."
+    )]
+    pub subject: String,
+    #[schemars(description = "The type of the reason for reporting.")]
+    pub reason_type: ReportReasonEnum,
+    #[schemars(description = "Optional additional context about the reason for reporting.")]
+    pub reason: Option<String>,
+    #[schemars(
+        description = "Optional DID of a labeler (moderation service) to send the report to, instead of the default moderation service."
+    )]
+    pub labeler: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportReasonEnum {
+    Spam,
+    Violation,
+    Misleading,
+    Sexual,
+    Rude,
+    Other,
+}
+
+impl fmt::Display for ReportReasonEnum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason_type = match self {
+            ReportReasonEnum::Spam => "com.atproto.moderation.defs#reasonSpam",
+            ReportReasonEnum::Violation => "com.atproto.moderation.defs#reasonViolation",
+            ReportReasonEnum::Misleading => "com.atproto.moderation.defs#reasonMisleading",
+            ReportReasonEnum::Sexual => "com.atproto.moderation.defs#reasonSexual",
+            ReportReasonEnum::Rude => "com.atproto.moderation.defs#reasonRude",
+            ReportReasonEnum::Other => "com.atproto.moderation.defs#reasonOther",
+        };
+        write!(f, "{reason_type}")
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetFeedViewPrefParams {
+    #[schemars(
+        description = "The URI of the feed, or an identifier which describes the feed. Defaults to `home`, the home timeline."
+    )]
+    pub feed: Option<String>,
+    #[schemars(description = "Whether to hide replies in the feed.")]
+    pub hide_replies: Option<bool>,
+    #[schemars(
+        description = "Whether to hide replies in the feed if they are not by followed users."
+    )]
+    pub hide_replies_by_unfollowed: Option<bool>,
+    #[schemars(description = "Whether to hide reposts in the feed.")]
+    pub hide_reposts: Option<bool>,
+    #[schemars(description = "Whether to hide quote posts in the feed.")]
+    pub hide_quote_posts: Option<bool>,
 }
@@ -1,28 +1,164 @@
+use crate::{
+    client::Agent,
+    types::{DEFAULT_LIMIT, RankByEnum, RepostsEnum},
+};
 use anyhow::anyhow;
-use bsky_sdk::{
-    BskyAgent,
-    api::{com::atproto, types::string::Datetime},
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use bsky_sdk::api::{
+    app::bsky,
+    com::atproto,
+    did_doc::DidDocument,
+    types::{
+        LimitedNonZeroU8, Union,
+        string::{AtIdentifier, Cid, Datetime, Did, Handle, Language, Nsid, RecordKey},
+    },
+    xrpc,
+};
+use chrono::{Local, TimeDelta, TimeZone, Utc};
+use rmcp::{
+    Error,
+    model::ErrorCode,
+    serde_json::{self, Map, Value, json},
 };
-use chrono::Local;
-use rmcp::serde_json::{self, Map, Value};
 use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
 
-pub async fn get_post(
-    agent: &BskyAgent,
-    at_uri: &str,
-) -> anyhow::Result<atproto::repo::get_record::Output> {
+/// Error code for requests that timed out, distinguished from other internal errors.
+pub const TIMEOUT_ERROR_CODE: ErrorCode = ErrorCode(-32001);
+/// Error code for requests rejected by the server's rate limit.
+pub const RATE_LIMIT_ERROR_CODE: ErrorCode = ErrorCode(-32002);
+
+/// Converts an optional limit to a limit parameter, defaulting to `DEFAULT_LIMIT` and clamping
+/// it to the range accepted by the API.
+pub fn limit_to_param<const MAX: u8>(limit: Option<u8>) -> Option<LimitedNonZeroU8<MAX>> {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX).try_into().ok()
+}
+
+/// Clamps a parameter to its maximum, returning a note for the user if it was clamped.
+pub fn clamp_param(name: &str, value: u16, max: u16) -> (u16, Option<String>) {
+    if value > max {
+        (
+            max,
+            Some(format!("{name} was clamped from {value} to {max}.")),
+        )
+    } else {
+        (value, None)
+    }
+}
+
+/// Splits an AT URI of a record into its repo, collection, and record key.
+pub fn parse_at_uri(at_uri: &str) -> anyhow::Result<(AtIdentifier, Nsid, RecordKey)> {
     let parts = at_uri
         .strip_prefix("at://")
         .ok_or(anyhow!("invalid AT URI"))?
         .splitn(3, '/')
         .collect::<Vec<_>>();
-    let repo = parts[0].parse().map_err(|e| anyhow!("invalid repo: {e}"))?;
-    let collection = parts[1]
-        .parse()
-        .map_err(|e| anyhow!("invalid collection: {e}"))?;
-    let rkey = parts[2]
-        .parse()
-        .map_err(|e| anyhow!("invalid record key: {e}"))?;
+    let [repo, collection, rkey] = parts[..] else {
+        return Err(anyhow!("invalid AT URI: expected a record URI"));
+    };
+    Ok((
+        repo.parse().map_err(|e| anyhow!("invalid repo: {e}"))?,
+        collection
+            .parse()
+            .map_err(|e| anyhow!("invalid collection: {e}"))?,
+        rkey.parse()
+            .map_err(|e| anyhow!("invalid record key: {e}"))?,
+    ))
+}
+
+/// Checks that an AT URI refers to a post, since other records (e.g. likes or profiles)
+/// cannot be replied to.
+pub fn check_reply_uri(at_uri: &str) -> Result<(), Error> {
+    let (_, collection, _) =
+        parse_at_uri(at_uri).map_err(map_invalid_params("failed to parse reply uri"))?;
+    if collection.as_str() != "app.bsky.feed.post" {
+        return Err(Error::invalid_params(
+            format!(
+                "reply target must be a post (app.bsky.feed.post), but {at_uri} is a {} record",
+                collection.as_str()
+            ),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+/// Converts an AT URI of a post to its `https://bsky.app` URL.
+pub fn post_web_url(at_uri: &str) -> anyhow::Result<String> {
+    let (repo, collection, rkey) = parse_at_uri(at_uri)?;
+    if collection.as_str() != "app.bsky.feed.post" {
+        return Err(anyhow!("not a post: {at_uri}"));
+    }
+    Ok(format!(
+        "https://bsky.app/profile/{}/post/{}",
+        repo.as_ref(),
+        rkey.as_str()
+    ))
+}
+
+/// Converts a reference to a post (or a feed generator or list), given as either an AT URI or a
+/// `https://bsky.app` URL, to an AT URI with the handle resolved to a DID.
+pub async fn normalize_post_ref(agent: &Agent, reference: &str) -> anyhow::Result<String> {
+    let reference = reference.trim();
+    if reference.starts_with("at://") {
+        let (repo, collection, rkey) = parse_at_uri(reference)?;
+        let did = resolve_identifier(agent, repo).await?;
+        return Ok(format!(
+            "at://{}/{}/{}",
+            did.as_str(),
+            collection.as_str(),
+            rkey.as_str()
+        ));
+    }
+    let Some(path) = reference
+        .strip_prefix("https://bsky.app/profile/")
+        .or_else(|| reference.strip_prefix("http://bsky.app/profile/"))
+    else {
+        return Ok(reference.to_string());
+    };
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let [actor, kind, rkey] = path.trim_end_matches('/').split('/').collect::<Vec<_>>()[..] else {
+        return Err(anyhow!("invalid bsky.app URL: {reference}"));
+    };
+    let collection = match kind {
+        "post" => "app.bsky.feed.post",
+        "feed" => "app.bsky.feed.generator",
+        "lists" => "app.bsky.graph.list",
+        _ => return Err(anyhow!("unsupported bsky.app URL: {reference}")),
+    };
+    let actor = actor
+        .parse::<AtIdentifier>()
+        .map_err(|e| anyhow!("invalid actor in bsky.app URL: {e}"))?;
+    let did = resolve_identifier(agent, actor).await?;
+    Ok(format!("at://{}/{collection}/{rkey}", did.as_str()))
+}
+
+/// Resolves a handle or DID to a DID.
+async fn resolve_identifier(agent: &Agent, identifier: AtIdentifier) -> anyhow::Result<Did> {
+    Ok(match identifier {
+        AtIdentifier::Did(did) => did,
+        AtIdentifier::Handle(handle) => {
+            agent
+                .api
+                .com
+                .atproto
+                .identity
+                .resolve_handle(atproto::identity::resolve_handle::ParametersData { handle }.into())
+                .await?
+                .data
+                .did
+        }
+    })
+}
+
+pub async fn get_post(
+    agent: &Agent,
+    at_uri: &str,
+) -> anyhow::Result<atproto::repo::get_record::Output> {
+    let (repo, collection, rkey) = parse_at_uri(at_uri)?;
     Ok(agent
         .api
         .com
@@ -40,6 +176,868 @@ pub async fn get_post(
         .await?)
 }
 
+/// Builds a strong reference to a record from its AT URI, fetching the record for its CID
+/// unless the CID is already known.
+pub async fn strong_ref_from_uri(
+    agent: &Agent,
+    at_uri: &str,
+    cid: Option<Cid>,
+) -> anyhow::Result<atproto::repo::strong_ref::Main> {
+    match cid {
+        Some(cid) => Ok(atproto::repo::strong_ref::MainData {
+            cid,
+            uri: at_uri.into(),
+        }
+        .into()),
+        None => strong_ref_from_record(&get_post(agent, at_uri).await?),
+    }
+}
+
+/// Builds a strong reference to a fetched record, with the canonical AT URI returned by the
+/// server (e.g. with the handle resolved to a DID).
+pub fn strong_ref_from_record(
+    output: &atproto::repo::get_record::Output,
+) -> anyhow::Result<atproto::repo::strong_ref::Main> {
+    Ok(atproto::repo::strong_ref::MainData {
+        cid: output
+            .data
+            .cid
+            .clone()
+            .ok_or(anyhow!("failed to get cid of {}", output.data.uri))?,
+        uri: output.data.uri.clone(),
+    }
+    .into())
+}
+
+/// Chooses the root of a reply from the result of fetching the root of the parent's thread.
+/// If the server responded that the root is not available (e.g. it was deleted), the parent
+/// becomes the root, while other errors (e.g. network errors) are returned.
+pub fn select_reply_root<T>(
+    parent: &atproto::repo::strong_ref::Main,
+    root: &atproto::repo::strong_ref::Main,
+    fetched: anyhow::Result<T>,
+) -> anyhow::Result<atproto::repo::strong_ref::Main> {
+    match fetched {
+        Ok(_) => Ok(root.clone()),
+        Err(e)
+            if e.downcast_ref::<xrpc::Error<atproto::repo::get_record::Error>>()
+                .is_some_and(is_record_not_found) =>
+        {
+            tracing::warn!(
+                "root post {} is not available, using the parent as root: {e}",
+                root.uri
+            );
+            Ok(parent.clone())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn resolve_did_document(
+    client: &reqwest::Client,
+    did: &Did,
+) -> anyhow::Result<DidDocument> {
+    let url = if let Some(id) = did.as_str().strip_prefix("did:web:") {
+        // Port numbers are percent-encoded in did:web identifiers
+        format!("https://{}/.well-known/did.json", id.replace("%3A", ":"))
+    } else if did.as_str().starts_with("did:plc:") {
+        format!("https://plc.directory/{}", did.as_str())
+    } else {
+        return Err(anyhow!("unsupported DID method: {}", did.as_str()));
+    };
+    Ok(client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<DidDocument>()
+        .await?)
+}
+
+pub fn xrpc_error<E>(message: &'static str, err: xrpc::Error<E>) -> Error
+where
+    xrpc::Error<E>: fmt::Display,
+{
+    let data = Some(Value::String(err.to_string()));
+    match &err {
+        xrpc::Error::HttpClient(e)
+            if e.downcast_ref::<reqwest::Error>()
+                .is_some_and(reqwest::Error::is_timeout) =>
+        {
+            Error::new(
+                TIMEOUT_ERROR_CODE,
+                format!("{message}: request timed out"),
+                data,
+            )
+        }
+        // The rate limit headers (e.g. `ratelimit-reset`) are added to the data by `call_tool`
+        xrpc::Error::XrpcResponse(e) if e.status.as_u16() == 429 => Error::new(
+            RATE_LIMIT_ERROR_CODE,
+            format!("{message}: rate limit exceeded, retry later"),
+            Some(json!({
+                "status": 429,
+                "error": err.to_string(),
+            })),
+        ),
+        _ => Error::internal_error(message, data),
+    }
+}
+
+/// Returns whether an error of `getRecord` means that the record does not exist (e.g. it was
+/// deleted), as opposed to other failures such as rate limits or server errors.
+pub fn is_record_not_found(err: &xrpc::Error<atproto::repo::get_record::Error>) -> bool {
+    match err {
+        xrpc::Error::XrpcResponse(e) if e.status.as_u16() == 400 => match &e.error {
+            Some(xrpc::error::XrpcErrorKind::Custom(
+                atproto::repo::get_record::Error::RecordNotFound(_),
+            )) => true,
+            Some(xrpc::error::XrpcErrorKind::Undefined(body)) => {
+                matches!(body.error.as_deref(), Some("RecordNotFound" | "NotFound"))
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Returns whether an error of `getAccountInviteCodes` means that account invites are disabled
+/// on the PDS.
+pub fn invites_disabled<E>(err: &xrpc::Error<E>) -> bool {
+    match err {
+        xrpc::Error::XrpcResponse(e) if e.status.as_u16() == 400 => matches!(
+            &e.error,
+            Some(xrpc::error::XrpcErrorKind::Undefined(body))
+                if body.error.as_deref() == Some("InvitesDisabled")
+        ),
+        _ => false,
+    }
+}
+
+/// Returns a function for `map_err` that converts an XRPC error with `xrpc_error`.
+pub fn map_xrpc_err<E>(message: &'static str) -> impl FnOnce(xrpc::Error<E>) -> Error
+where
+    xrpc::Error<E>: fmt::Display,
+{
+    move |err| xrpc_error(message, err)
+}
+
+/// Returns a function for `map_err` that converts an error caused by the given parameters to an
+/// invalid params error with its description as data.
+pub fn map_invalid_params<E: fmt::Display>(message: &'static str) -> impl FnOnce(E) -> Error {
+    move |err| Error::invalid_params(message, Some(Value::String(err.to_string())))
+}
+
+/// Returns a function for `map_err` that converts any error to an internal error with
+/// its description as data, or to a timeout error if a request timed out.
+pub fn map_internal_err<E>(message: &'static str) -> impl FnOnce(E) -> Error
+where
+    E: Into<anyhow::Error>,
+{
+    move |err| {
+        let err = err.into();
+        let data = Some(Value::String(format!("{err:#}")));
+        if is_timeout(&err) {
+            Error::new(
+                TIMEOUT_ERROR_CODE,
+                format!("{message}: request timed out"),
+                data,
+            )
+        } else {
+            Error::internal_error(message, data)
+        }
+    }
+}
+
+/// Returns whether an error was caused by a request that timed out.
+fn is_timeout(err: &anyhow::Error) -> bool {
+    err.chain().any(|e| {
+        e.downcast_ref::<reqwest::Error>()
+            .is_some_and(reqwest::Error::is_timeout)
+    })
+        // bsky-sdk flattens client errors into their debug representation, e.g. `TimedOut`
+        || format!("{err:#}").contains("TimedOut")
+}
+
+/// Metadata of a web page for an external link card, from its OpenGraph tags.
+#[derive(Debug, Default)]
+pub struct LinkCard {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+}
+
+/// Fetches a web page and parses its `og:title`, `og:description`, and `og:image` tags.
+pub async fn fetch_link_card(client: &reqwest::Client, url: &str) -> anyhow::Result<LinkCard> {
+    let html = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let mut card = LinkCard::default();
+    for tag in html.split("<meta").skip(1) {
+        let tag = tag.split('>').next().unwrap_or_default();
+        let Some(property) =
+            html_attribute(tag, "property").or_else(|| html_attribute(tag, "name"))
+        else {
+            continue;
+        };
+        let field = match property.as_str() {
+            "og:title" => &mut card.title,
+            "og:description" => &mut card.description,
+            "og:image" => &mut card.image,
+            _ => continue,
+        };
+        if field.is_none() {
+            *field = html_attribute(tag, "content").filter(|content| !content.is_empty());
+        }
+    }
+    Ok(card)
+}
+
+/// Returns the unescaped value of a quoted attribute in the inside of an HTML tag.
+fn html_attribute(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag;
+    while let Some(pos) = rest.find(name) {
+        let preceded_by_space = rest[..pos].ends_with(char::is_whitespace);
+        rest = &rest[pos + name.len()..];
+        let Some(value) = rest.trim_start().strip_prefix('=') else {
+            continue;
+        };
+        if !preceded_by_space {
+            continue;
+        }
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+        let value = &value[1..];
+        let end = value.find(quote)?;
+        return Some(
+            value[..end]
+                .replace("&quot;", "\"")
+                .replace("&#39;", "'")
+                .replace("&#x27;", "'")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&amp;", "&"),
+        );
+    }
+    None
+}
+
+/// Fetches the content of a URL, such as an image.
+pub async fn fetch_bytes(client: &reqwest::Client, url: &str) -> anyhow::Result<Vec<u8>> {
+    Ok(client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?
+        .to_vec())
+}
+
+/// Detects the MIME type of image data from its magic bytes.
+pub fn detect_image_mime_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// Parses a datetime in ISO 8601 format, or a relative expression such as `30m`, `24h`, `7d`,
+/// `2w`, `now`, `today`, or `yesterday`.
+pub fn parse_datetime(value: &str) -> anyhow::Result<Datetime> {
+    let value = value.trim();
+    let now = Local::now();
+    let datetime = match value {
+        "now" => now,
+        "today" | "yesterday" => {
+            let date = if value == "today" {
+                now.date_naive()
+            } else {
+                now.date_naive() - TimeDelta::days(1)
+            };
+            Local
+                .from_local_datetime(&date.and_time(Default::default()))
+                .earliest()
+                .ok_or(anyhow!("invalid datetime: {value}"))?
+        }
+        _ => match parse_relative(value) {
+            Some(delta) => now - delta,
+            None => {
+                return value
+                    .parse()
+                    .map_err(|e| anyhow!("invalid datetime {value:?}: {e}"));
+            }
+        },
+    };
+    Ok(Datetime::new(datetime.fixed_offset()))
+}
+
+/// Parses a duration given as a relative expression such as `30m`, `24h`, `7d`, or `2w`.
+pub fn parse_duration(value: &str) -> anyhow::Result<TimeDelta> {
+    parse_relative(value.trim())
+        .filter(|delta| *delta > TimeDelta::zero())
+        .ok_or(anyhow!("invalid duration {value:?}"))
+}
+
+fn parse_relative(value: &str) -> Option<TimeDelta> {
+    let unit = value.chars().last()?;
+    let amount = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    match unit {
+        'm' => TimeDelta::try_minutes(amount),
+        'h' => TimeDelta::try_hours(amount),
+        'd' => TimeDelta::try_days(amount),
+        'w' => TimeDelta::try_weeks(amount),
+        _ => None,
+    }
+}
+
+/// Excludes or tags the reposts in an array of feed items.
+pub fn process_reposts(feed: &mut Value, reposts: &RepostsEnum) {
+    let Value::Array(items) = feed else {
+        return;
+    };
+    let is_repost = |item: &Value| {
+        item.pointer("/reason/$type")
+            .is_some_and(|t| t == "app.bsky.feed.defs#reasonRepost")
+    };
+    match reposts {
+        RepostsEnum::Include => {}
+        RepostsEnum::Exclude => items.retain(|item| !is_repost(item)),
+        RepostsEnum::Tag => {
+            for item in items.iter_mut() {
+                let repost = is_repost(item);
+                if let Value::Object(map) = item {
+                    map.insert("isRepost".into(), Value::Bool(repost));
+                }
+            }
+        }
+    }
+}
+
+/// Parses a language code, which must be a well-formed BCP-47 tag.
+pub fn parse_language(value: &str) -> anyhow::Result<Language> {
+    value.trim().parse().map_err(|e| {
+        anyhow!("invalid language tag `{value}` (expected BCP-47, e.g. `en` or `pt-BR`): {e}")
+    })
+}
+
+/// Finds `@handle` mentions in text, returning the byte offset of each `@` with the handle.
+pub fn find_mentions(text: &str) -> Vec<(usize, String)> {
+    let mut mentions = Vec::new();
+    let mut prev = None;
+    for (i, c) in text.char_indices() {
+        // A mention must start the text or follow a whitespace or an opening parenthesis
+        if c == '@' && prev.is_none_or(|prev: char| prev.is_whitespace() || prev == '(') {
+            let handle = text[i + 1..]
+                .split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-'))
+                .next()
+                .unwrap_or_default()
+                .trim_end_matches('.');
+            if handle.parse::<Handle>().is_ok() {
+                mentions.push((i, handle.to_string()));
+            }
+        }
+        prev = Some(c);
+    }
+    mentions
+}
+
+/// Checks that all mentions in a text were resolved to facets, failing with the unresolved
+/// handles otherwise.
+pub fn check_mentions_resolved(
+    text: &str,
+    facets: &[bsky::richtext::facet::Main],
+) -> Result<(), Error> {
+    // Resolved mentions get a facet starting at the `@`, so the others were not resolved
+    let facet_starts = facets
+        .iter()
+        .map(|facet| facet.index.byte_start)
+        .collect::<HashSet<_>>();
+    let unresolved = find_mentions(text)
+        .into_iter()
+        .filter(|(start, _)| !facet_starts.contains(start))
+        .map(|(_, handle)| format!("@{handle}"))
+        .collect::<Vec<_>>();
+    if !unresolved.is_empty() {
+        return Err(Error::invalid_params(
+            format!("failed to resolve mentions: {}", unresolved.join(", ")),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+/// Rewrites the text of link facets to a shortened form of the URL, as the official client does,
+/// adjusting the facet indices while keeping their URIs. Facets overlapping a previous facet are
+/// dropped, since their indices cannot be mapped to the rewritten text.
+pub fn shorten_links(text: &str, facets: &mut Vec<bsky::richtext::facet::Main>) -> String {
+    fn shorten(url: &str) -> String {
+        let url = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))
+            .unwrap_or(url);
+        let url = url.strip_prefix("www.").unwrap_or(url);
+        let url = url.trim_end_matches('/');
+        if url.chars().count() > 30 {
+            format!("{}...", url.chars().take(27).collect::<String>())
+        } else {
+            url.to_string()
+        }
+    }
+    facets.sort_by_key(|facet| facet.index.byte_start);
+    let mut shortened = String::with_capacity(text.len());
+    let mut last = 0;
+    facets.retain_mut(|facet| {
+        let (start, end) = (facet.index.byte_start, facet.index.byte_end);
+        if start < last || end > text.len() {
+            return false;
+        }
+        shortened.push_str(&text[last..start]);
+        let is_link = facet.features.iter().any(|feature| {
+            matches!(
+                feature,
+                Union::Refs(bsky::richtext::facet::MainFeaturesItem::Link(_))
+            )
+        });
+        let new_start = shortened.len();
+        if is_link {
+            shortened.push_str(&shorten(&text[start..end]));
+        } else {
+            shortened.push_str(&text[start..end]);
+        }
+        facet.index.byte_start = new_start;
+        facet.index.byte_end = shortened.len();
+        last = end;
+        true
+    });
+    shortened.push_str(&text[last..]);
+    shortened
+}
+
+/// Sorts posts in descending order of the ranking, keeping the server order for ties.
+pub fn rank_posts(posts: &mut [bsky::feed::defs::PostView], rank_by: &RankByEnum) {
+    let count = |count: Option<i64>| count.unwrap_or_default() as f64;
+    let now = Utc::now();
+    let score = |post: &bsky::feed::defs::PostView| match rank_by {
+        RankByEnum::Server => 0.0,
+        RankByEnum::Likes => count(post.like_count),
+        RankByEnum::Reposts => count(post.repost_count),
+        RankByEnum::Replies => count(post.reply_count),
+        RankByEnum::Engagement => {
+            let engagement = count(post.like_count)
+                + 2.0 * count(post.repost_count)
+                + 2.0 * count(post.quote_count)
+                + count(post.reply_count);
+            // Gravity-style decay, so that fresh posts can outrank older popular ones
+            let hours = (now - post.indexed_at.as_ref().to_utc())
+                .num_minutes()
+                .max(0) as f64
+                / 60.0;
+            (engagement + 1.0) / (hours + 2.0).powf(1.5)
+        }
+    };
+    posts.sort_by(|a, b| score(b).total_cmp(&score(a)));
+}
+
+/// Inlines the posts quoted by posts in the value as `quotedPost`, where the quoted post is only
+/// referenced by the record and not hydrated, up to `embed_depth` levels of nested quotes.
+pub async fn hydrate_quotes(agent: &Agent, value: &mut Value, embed_depth: u8) {
+    // Each round may reveal quotes of the newly inlined posts, one level deeper
+    for _ in 0..embed_depth {
+        let mut uris = Vec::new();
+        visit_unhydrated_quotes(value, 0, &mut |_, uri, level| {
+            if level < embed_depth {
+                uris.push(uri.to_string());
+            }
+        });
+        uris.sort();
+        uris.dedup();
+        if uris.is_empty() {
+            return;
+        }
+        let mut quoted_posts = HashMap::new();
+        for chunk in uris.chunks(25) {
+            let output = match agent
+                .api
+                .app
+                .bsky
+                .feed
+                .get_posts(
+                    bsky::feed::get_posts::ParametersData {
+                        uris: chunk.to_vec(),
+                    }
+                    .into(),
+                )
+                .await
+            {
+                Ok(output) => output,
+                Err(e) => {
+                    tracing::warn!("failed to get quoted posts: {e}");
+                    return;
+                }
+            };
+            for post in output.data.posts {
+                let record = serde_json::to_value(&post.record).unwrap_or_default();
+                let quoted_post = json!({
+                    "uri": post.uri,
+                    "author": {
+                        "did": post.author.did,
+                        "handle": post.author.handle,
+                        "displayName": post.author.display_name,
+                    },
+                    "text": record.get("text"),
+                    "createdAt": record.get("createdAt"),
+                    "record": record,
+                });
+                if let Ok(quoted_post) = convert_datetime(quoted_post) {
+                    quoted_posts.insert(post.uri.clone(), quoted_post);
+                }
+            }
+        }
+        visit_unhydrated_quotes(value, 0, &mut |map, uri, level| {
+            if level < embed_depth {
+                if let Some(quoted_post) = quoted_posts.get(uri) {
+                    map.insert("quotedPost".into(), quoted_post.clone());
+                }
+            }
+        });
+    }
+}
+
+/// Adds `video` with the HLS playlist URL, thumbnail, and alt text to each post view (or
+/// quoted record view) that has a video embed, including one with media of a quote.
+pub fn surface_videos(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let video = ["embed", "embeds"]
+                .iter()
+                .filter_map(|key| map.get(*key))
+                .flat_map(|embed| match embed {
+                    Value::Array(embeds) => embeds.iter().collect(),
+                    embed => vec![embed],
+                })
+                .find_map(|embed| {
+                    let embed = match embed.get("$type")?.as_str()? {
+                        "app.bsky.embed.recordWithMedia#view" => embed.get("media")?,
+                        _ => embed,
+                    };
+                    (embed.get("$type")? == "app.bsky.embed.video#view").then(|| {
+                        json!({
+                            "playlist": embed.get("playlist"),
+                            "thumbnail": embed.get("thumbnail"),
+                            "alt": embed.get("alt"),
+                            "aspectRatio": embed.get("aspectRatio"),
+                        })
+                    })
+                });
+            for child in map.values_mut() {
+                surface_videos(child);
+            }
+            if let Some(video) = video {
+                map.insert("video".into(), video);
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(surface_videos),
+        _ => {}
+    }
+}
+
+/// Calls `f` with each post view that quotes a post without a hydrated embed, the quoted URI,
+/// and the number of quotes the post view is nested in.
+fn visit_unhydrated_quotes<F>(value: &mut Value, level: u8, f: &mut F)
+where
+    F: FnMut(&mut Map<String, Value>, &str, u8),
+{
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                visit_unhydrated_quotes(item, level, f);
+            }
+        }
+        Value::Object(map) => {
+            // Post views have the record in `record`, while quoted post views have it in `value`
+            let quoted_uri = ["record", "value"]
+                .iter()
+                .find_map(|key| map.get(*key).and_then(quoted_post_uri))
+                .map(String::from);
+            if let Some(uri) = quoted_uri {
+                let hydrated = ["embed", "embeds", "quotedPost"]
+                    .iter()
+                    .any(|key| map.contains_key(*key));
+                if map.contains_key("author") && !hydrated {
+                    f(map, &uri, level);
+                }
+            }
+            for (key, child) in map.iter_mut() {
+                let level = match key.as_str() {
+                    "embed" | "embeds" | "quotedPost" => level.saturating_add(1),
+                    _ => level,
+                };
+                visit_unhydrated_quotes(child, level, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the URI of the post quoted by a post record, if any.
+fn quoted_post_uri(record: &Value) -> Option<&str> {
+    let embed = record.get("embed")?;
+    let uri = match embed.get("$type")?.as_str()? {
+        "app.bsky.embed.record" => embed.pointer("/record/uri"),
+        "app.bsky.embed.recordWithMedia" => embed.pointer("/record/record/uri"),
+        _ => None,
+    }?;
+    uri.as_str()
+        .filter(|uri| uri.contains("/app.bsky.feed.post/"))
+}
+
+/// Returns whether any of the fetched direct replies to a post of a thread is by the given DID.
+pub fn replied_by(node: &Value, did: &str) -> bool {
+    node["replies"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|reply| reply.pointer("/post/author/did").is_some_and(|d| d == did))
+}
+
+/// Returns the number of replies beneath a post of a thread (at any depth, e.g. replies to the
+/// given DID's own reply) by others that are newer than the last reply by the given DID, or
+/// `None` if the DID has not replied to the post.
+pub fn new_replies_since(node: &Value, did: &str) -> Option<usize> {
+    fn collect<'a>(node: &'a Value, replies: &mut Vec<&'a Value>) {
+        for reply in node["replies"].as_array().into_iter().flatten() {
+            replies.push(reply);
+            collect(reply, replies);
+        }
+    }
+
+    if !replied_by(node, did) {
+        return None;
+    }
+    let mut replies = Vec::new();
+    collect(node, &mut replies);
+    let posts = replies
+        .into_iter()
+        .filter_map(|reply| {
+            let indexed_at = reply
+                .pointer("/post/indexedAt")
+                .and_then(Value::as_str)?
+                .parse::<Datetime>()
+                .ok()?;
+            let is_mine = reply.pointer("/post/author/did").is_some_and(|d| d == did);
+            Some((indexed_at, is_mine))
+        })
+        .collect::<Vec<_>>();
+    let last_reply = posts
+        .iter()
+        .filter(|(_, is_mine)| *is_mine)
+        .map(|(indexed_at, _)| indexed_at.as_ref())
+        .max()?;
+    Some(
+        posts
+            .iter()
+            .filter(|(indexed_at, is_mine)| !is_mine && indexed_at.as_ref() > last_reply)
+            .count(),
+    )
+}
+
+/// Marks each post of a thread and its replies with `repliedByMe`, whether any of its fetched
+/// direct replies is by the given DID.
+pub fn annotate_replied_by(node: &mut Value, did: &str) {
+    let replied = replied_by(node, did);
+    let Value::Object(map) = node else {
+        return;
+    };
+    let Some(Value::Array(replies)) = map.get_mut("replies") else {
+        return;
+    };
+    for reply in replies.iter_mut() {
+        annotate_replied_by(reply, did);
+    }
+    map.insert("repliedByMe".into(), Value::Bool(replied));
+}
+
+/// Keeps only the `max` most liked replies of each node of a thread, marking the nodes
+/// with the number of removed replies as `more`.
+pub fn limit_replies(node: &mut Value, max: usize) {
+    let Value::Object(map) = node else {
+        return;
+    };
+    let Some(Value::Array(replies)) = map.get_mut("replies") else {
+        return;
+    };
+    let removed = replies.len().saturating_sub(max);
+    if removed > 0 {
+        // Replies that are not found or blocked have no like count and come last
+        replies.sort_by_key(|reply| std::cmp::Reverse(reply["post"]["likeCount"].as_i64()));
+        replies.truncate(max);
+    }
+    for reply in replies.iter_mut() {
+        limit_replies(reply, max);
+    }
+    if removed > 0 {
+        map.insert("more".into(), json!(removed));
+    }
+}
+
+/// Removes the deepest levels of replies of a thread so that its serialized size fits in
+/// `max_bytes`, marking the nodes whose replies were removed with `truncated: true`.
+/// Returns whether any replies were removed.
+pub fn truncate_thread(thread: &mut Value, max_bytes: usize) -> bool {
+    // Upper bound of the bytes of `,"replies":[]` and `,"truncated":true` in a node, and the
+    // comma separating it from its siblings
+    const NODE_OVERHEAD: usize = 31;
+    // Adds the size of each node without its replies to the total of its depth, serializing
+    // every node only once
+    fn measure(node: &mut Value, depth: usize, sizes: &mut Vec<usize>) {
+        let Value::Object(map) = node else {
+            return;
+        };
+        let replies = map.remove("replies");
+        if sizes.len() <= depth {
+            sizes.resize(depth + 1, 0);
+        }
+        sizes[depth] += serde_json::to_vec(&*map).map_or(0, |bytes| bytes.len()) + NODE_OVERHEAD;
+        if let Some(mut replies) = replies {
+            for reply in replies.as_array_mut().into_iter().flatten() {
+                measure(reply, depth + 1, sizes);
+            }
+            map.insert("replies".into(), replies);
+        }
+    }
+    fn prune(node: &mut Value, depth: usize) {
+        let Value::Object(map) = node else {
+            return;
+        };
+        if depth == 0 {
+            if map
+                .get("replies")
+                .and_then(Value::as_array)
+                .is_some_and(|replies| !replies.is_empty())
+            {
+                map.insert("replies".into(), Value::Array(Vec::new()));
+                map.insert("truncated".into(), Value::Bool(true));
+            }
+        } else if let Some(Value::Array(replies)) = map.get_mut("replies") {
+            for reply in replies {
+                prune(reply, depth - 1);
+            }
+        }
+    }
+    let mut sizes = Vec::new();
+    measure(thread, 0, &mut sizes);
+    // Keep as many levels as fit, but at least the root post even if it does not fit by itself
+    let mut total = 0;
+    let levels = sizes
+        .iter()
+        .take_while(|size| {
+            total += *size;
+            total <= max_bytes
+        })
+        .count()
+        .max(1);
+    if levels >= sizes.len() {
+        return false;
+    }
+    prune(thread, levels - 1);
+    true
+}
+
+/// Counts the posts of a thread (parents and replies) that were not found or are blocked.
+pub fn count_unavailable_posts(thread: &Value) -> (usize, usize) {
+    fn count(node: &Value, counts: &mut (usize, usize), parents: bool, replies: bool) {
+        match node.get("$type").and_then(Value::as_str) {
+            Some("app.bsky.feed.defs#notFoundPost") => counts.0 += 1,
+            Some("app.bsky.feed.defs#blockedPost") => counts.1 += 1,
+            _ => {}
+        }
+        if parents {
+            if let Some(parent) = node.get("parent") {
+                count(parent, counts, true, false);
+            }
+        }
+        if replies {
+            for reply in node["replies"].as_array().into_iter().flatten() {
+                count(reply, counts, false, true);
+            }
+        }
+    }
+    let mut counts = (0, 0);
+    count(thread, &mut counts, true, true);
+    counts
+}
+
+/// Renders a thread as transcript lines of `@handle: text (timestamp)`, starting from the
+/// topmost parent and walking the replies depth-first.
+pub fn render_thread_transcript(thread: &Value) -> String {
+    fn line(node: &Value) -> String {
+        match node.get("$type").and_then(Value::as_str) {
+            Some("app.bsky.feed.defs#notFoundPost") => "(post not found)".into(),
+            Some("app.bsky.feed.defs#blockedPost") => "(blocked post)".into(),
+            _ => {
+                let post = &node["post"];
+                format!(
+                    "@{}: {} ({})",
+                    post["author"]["handle"].as_str().unwrap_or_default(),
+                    post["record"]["text"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .replace('\n', " "),
+                    post["record"]["createdAt"].as_str().unwrap_or_default()
+                )
+            }
+        }
+    }
+    fn replies(node: &Value, lines: &mut Vec<String>) {
+        for reply in node["replies"].as_array().into_iter().flatten() {
+            lines.push(line(reply));
+            replies(reply, lines);
+        }
+    }
+    let mut parents = Vec::new();
+    let mut parent = thread.get("parent");
+    while let Some(node) = parent {
+        parents.push(line(node));
+        parent = node.get("parent");
+    }
+    let mut lines = parents.into_iter().rev().collect::<Vec<_>>();
+    lines.push(line(thread));
+    replies(thread, &mut lines);
+    lines.join("\n")
+}
+
+/// Returns the expiration time (`exp` claim, in seconds since the epoch) of a JWT.
+pub fn jwt_expiry(token: &str) -> Option<i64> {
+    let payload = BASE64_URL_SAFE_NO_PAD
+        .decode(token.split('.').nth(1)?)
+        .ok()?;
+    serde_json::from_slice::<Value>(&payload).ok()?["exp"].as_i64()
+}
+
+/// Substitutes `{name}` placeholders in a prompt template with the argument values.
+pub fn fill_prompt(template: &str, arguments: &Map<String, Value>) -> String {
+    arguments
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                value => value.to_string(),
+            };
+            text.replace(&format!("{{{name}}}"), &value)
+        })
+}
+
 pub fn convert_datetime<S>(data: S) -> Result<Value, serde_json::Error>
 where
     S: Serialize,
@@ -76,3 +1074,358 @@ where
     }
     recursive(value)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MAX_DEPTH, MAX_PARENT_HEIGHT};
+
+    const CID: &str = "bafyreidfayvfuwqa7qlnopdjiqrxzs6blmoeu4rujcjtnci5beludirz2a";
+
+    fn post_ref(rkey: &str) -> atproto::repo::strong_ref::Main {
+        atproto::repo::strong_ref::MainData {
+            cid: CID.parse().expect("failed to parse cid"),
+            uri: format!("at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post/{rkey}"),
+        }
+        .into()
+    }
+
+    #[test]
+    fn parse_duration_parses_relative_expressions() {
+        assert_eq!(parse_duration("30m").ok(), Some(TimeDelta::minutes(30)));
+        assert_eq!(parse_duration(" 1d ").ok(), Some(TimeDelta::days(1)));
+        assert!(parse_duration("0h").is_err());
+        assert!(parse_duration("tomorrow").is_err());
+    }
+
+    #[test]
+    fn limit_to_param_defaults_if_none() {
+        let limit = limit_to_param::<100>(None).map(|limit| limit.get());
+        assert_eq!(limit, Some(DEFAULT_LIMIT));
+    }
+
+    #[test]
+    fn limit_to_param_raises_zero_to_one() {
+        let limit = limit_to_param::<100>(Some(0)).map(|limit| limit.get());
+        assert_eq!(limit, Some(1));
+    }
+
+    #[test]
+    fn limit_to_param_keeps_limits_in_range() {
+        let limit = limit_to_param::<100>(Some(DEFAULT_LIMIT)).map(|limit| limit.get());
+        assert_eq!(limit, Some(DEFAULT_LIMIT));
+        let limit = limit_to_param::<100>(Some(100)).map(|limit| limit.get());
+        assert_eq!(limit, Some(100));
+    }
+
+    #[test]
+    fn limit_to_param_clamps_limits_over_max() {
+        let limit = limit_to_param::<100>(Some(101)).map(|limit| limit.get());
+        assert_eq!(limit, Some(100));
+        let limit = limit_to_param::<50>(Some(u8::MAX)).map(|limit| limit.get());
+        assert_eq!(limit, Some(50));
+    }
+
+    #[test]
+    fn clamp_param_keeps_values_up_to_max() {
+        assert_eq!(clamp_param("depth", 0, MAX_DEPTH), (0, None));
+        assert_eq!(
+            clamp_param("depth", MAX_DEPTH - 1, MAX_DEPTH),
+            (MAX_DEPTH - 1, None)
+        );
+        assert_eq!(
+            clamp_param("depth", MAX_DEPTH, MAX_DEPTH),
+            (MAX_DEPTH, None)
+        );
+        assert_eq!(
+            clamp_param("parent_height", MAX_PARENT_HEIGHT, MAX_PARENT_HEIGHT),
+            (MAX_PARENT_HEIGHT, None)
+        );
+    }
+
+    #[test]
+    fn clamp_param_clamps_values_above_max() {
+        let (depth, note) = clamp_param("depth", MAX_DEPTH + 1, MAX_DEPTH);
+        assert_eq!(depth, MAX_DEPTH);
+        assert_eq!(
+            note.as_deref(),
+            Some(format!("depth was clamped from {} to {MAX_DEPTH}.", MAX_DEPTH + 1).as_str())
+        );
+        let (parent_height, note) = clamp_param("parent_height", u16::MAX, MAX_PARENT_HEIGHT);
+        assert_eq!(parent_height, MAX_PARENT_HEIGHT);
+        assert!(note.is_some());
+    }
+
+    #[test]
+    fn check_reply_uri_accepts_posts() {
+        assert!(
+            check_reply_uri(
+                "at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post/3l3qo2vutsw2b"
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn check_reply_uri_rejects_other_records() {
+        let err = check_reply_uri(
+            "at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.like/3l3qo2vutsw2b",
+        )
+        .expect_err("a like cannot be replied to");
+        assert_eq!(err.code, ErrorCode::INVALID_PARAMS);
+        assert!(err.message.contains("app.bsky.feed.like"));
+    }
+
+    #[test]
+    fn find_mentions_finds_handles() {
+        assert_eq!(
+            find_mentions("hi @alice.bsky.social and (@bob.example.com)."),
+            vec![
+                (3, "alice.bsky.social".to_string()),
+                (27, "bob.example.com".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn find_mentions_ignores_non_mentions() {
+        assert!(find_mentions("mail me at alice@example.com").is_empty());
+        assert!(find_mentions("@alice is not a handle").is_empty());
+    }
+
+    #[test]
+    fn check_mentions_resolved_accepts_resolved_mentions() {
+        let facet = serde_json::from_value::<bsky::richtext::facet::Main>(json!({
+            "index": { "byteStart": 3, "byteEnd": 21 },
+            "features": [{
+                "$type": "app.bsky.richtext.facet#mention",
+                "did": "did:plc:z72i7hdynmk6r22z27h6tvur",
+            }],
+        }))
+        .expect("failed to build facet");
+        assert!(check_mentions_resolved("hi @alice.bsky.social", &[facet]).is_ok());
+    }
+
+    #[test]
+    fn check_mentions_resolved_rejects_unresolvable_handles() {
+        let err = check_mentions_resolved("hi @nobody.example.com", &[])
+            .expect_err("the mention should be unresolved");
+        assert_eq!(err.code, ErrorCode::INVALID_PARAMS);
+        assert!(err.message.contains("@nobody.example.com"));
+    }
+
+    #[test]
+    fn shorten_links_drops_overlapping_facets() {
+        let text = "a https://example.com/a/very/long/path/to/a/page b https://www.example.org/ c @alice.test";
+        let facet = |start: usize, end: usize, feature: Value| {
+            serde_json::from_value::<bsky::richtext::facet::Main>(json!({
+                "index": { "byteStart": start, "byteEnd": end },
+                "features": [feature],
+            }))
+            .expect("failed to build facet")
+        };
+        let link = |url: &str| {
+            let start = text.find(url).expect("url should be in the text");
+            facet(
+                start,
+                start + url.len(),
+                json!({ "$type": "app.bsky.richtext.facet#link", "uri": url }),
+            )
+        };
+        let mention = |start: usize, end: usize| {
+            facet(
+                start,
+                end,
+                json!({ "$type": "app.bsky.richtext.facet#mention", "did": "did:plc:alice" }),
+            )
+        };
+        let first = text
+            .find("https://example.com")
+            .expect("url should be in the text");
+        let last = text
+            .find("@alice.test")
+            .expect("mention should be in the text");
+        let mut facets = vec![
+            mention(last, text.len()),
+            link("https://www.example.org/"),
+            mention(first + 8, first + 19),
+            link("https://example.com/a/very/long/path/to/a/page"),
+        ];
+        let shortened = shorten_links(text, &mut facets);
+        assert_eq!(
+            shortened,
+            "a example.com/a/very/long/pat... b example.org c @alice.test"
+        );
+        let texts = facets
+            .iter()
+            .map(|facet| &shortened[facet.index.byte_start..facet.index.byte_end])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            [
+                "example.com/a/very/long/pat...",
+                "example.org",
+                "@alice.test"
+            ]
+        );
+    }
+
+    #[test]
+    fn truncate_thread_removes_deepest_replies() {
+        fn node(text: &str, replies: Vec<Value>) -> Value {
+            json!({ "post": { "text": text.repeat(100) }, "replies": replies })
+        }
+        let mut thread = node(
+            "a",
+            vec![node("b", vec![node("c", vec![node("d", Vec::new())])])],
+        );
+        let max_bytes = 500;
+        assert!(truncate_thread(&mut thread, max_bytes));
+        assert!(
+            serde_json::to_vec(&thread)
+                .expect("failed to serialize")
+                .len()
+                <= max_bytes
+        );
+        assert_eq!(thread["replies"][0]["replies"][0]["truncated"], json!(true));
+        assert_eq!(thread["replies"][0]["replies"][0]["replies"], json!([]));
+    }
+
+    #[test]
+    fn truncate_thread_keeps_threads_that_fit() {
+        let mut thread =
+            json!({ "post": { "text": "a" }, "replies": [{ "post": { "text": "b" } }] });
+        let original = thread.clone();
+        assert!(!truncate_thread(&mut thread, 1000));
+        assert_eq!(thread, original);
+    }
+
+    fn reply(did: &str, indexed_at: &str, replies: Vec<Value>) -> Value {
+        json!({
+            "post": { "author": { "did": did }, "indexedAt": indexed_at },
+            "replies": replies,
+        })
+    }
+
+    #[test]
+    fn new_replies_since_counts_replies_to_my_reply() {
+        let thread = reply(
+            "did:plc:other",
+            "2025-01-01T00:00:00.000Z",
+            vec![reply(
+                "did:plc:me",
+                "2025-01-01T01:00:00.000Z",
+                vec![reply(
+                    "did:plc:other",
+                    "2025-01-01T02:00:00.000Z",
+                    vec![reply(
+                        "did:plc:third",
+                        "2025-01-01T03:00:00.000Z",
+                        Vec::new(),
+                    )],
+                )],
+            )],
+        );
+        assert_eq!(new_replies_since(&thread, "did:plc:me"), Some(2));
+    }
+
+    #[test]
+    fn new_replies_since_excludes_replies_before_my_last_reply() {
+        let thread = reply(
+            "did:plc:other",
+            "2025-01-01T00:00:00.000Z",
+            vec![
+                reply("did:plc:third", "2025-01-01T00:30:00.000Z", Vec::new()),
+                reply(
+                    "did:plc:me",
+                    "2025-01-01T01:00:00.000Z",
+                    vec![reply(
+                        "did:plc:other",
+                        "2025-01-01T02:00:00.000Z",
+                        vec![reply("did:plc:me", "2025-01-01T03:00:00.000Z", Vec::new())],
+                    )],
+                ),
+            ],
+        );
+        assert_eq!(new_replies_since(&thread, "did:plc:me"), Some(0));
+    }
+
+    #[test]
+    fn new_replies_since_requires_a_direct_reply() {
+        let thread = reply(
+            "did:plc:other",
+            "2025-01-01T00:00:00.000Z",
+            vec![reply(
+                "did:plc:third",
+                "2025-01-01T01:00:00.000Z",
+                Vec::new(),
+            )],
+        );
+        assert_eq!(new_replies_since(&thread, "did:plc:me"), None);
+    }
+
+    #[test]
+    fn select_reply_root_keeps_available_root() {
+        let (parent, root) = (post_ref("parent"), post_ref("root"));
+        let selected = select_reply_root(&parent, &root, Ok(())).expect("root should be selected");
+        assert_eq!(selected.uri, root.uri);
+    }
+
+    #[test]
+    fn select_reply_root_falls_back_to_parent_if_root_is_deleted() {
+        let (parent, root) = (post_ref("parent"), post_ref("root"));
+        let err =
+            xrpc::Error::<atproto::repo::get_record::Error>::XrpcResponse(xrpc::error::XrpcError {
+                status: xrpc::http::StatusCode::BAD_REQUEST,
+                error: Some(xrpc::error::XrpcErrorKind::Undefined(
+                    xrpc::error::ErrorResponseBody {
+                        error: Some("RecordNotFound".into()),
+                        message: Some("Could not locate record".into()),
+                    },
+                )),
+            });
+        let selected = select_reply_root(&parent, &root, Err::<(), _>(err.into()))
+            .expect("parent should be selected");
+        assert_eq!(selected.uri, parent.uri);
+    }
+
+    #[test]
+    fn select_reply_root_returns_other_error_responses() {
+        let (parent, root) = (post_ref("parent"), post_ref("root"));
+        for (status, error) in [
+            (
+                xrpc::http::StatusCode::TOO_MANY_REQUESTS,
+                "RateLimitExceeded",
+            ),
+            (
+                xrpc::http::StatusCode::INTERNAL_SERVER_ERROR,
+                "InternalServerError",
+            ),
+        ] {
+            let err = xrpc::Error::<atproto::repo::get_record::Error>::XrpcResponse(
+                xrpc::error::XrpcError {
+                    status,
+                    error: Some(xrpc::error::XrpcErrorKind::Undefined(
+                        xrpc::error::ErrorResponseBody {
+                            error: Some(error.into()),
+                            message: None,
+                        },
+                    )),
+                },
+            );
+            assert!(
+                select_reply_root(&parent, &root, Err::<(), _>(err.into())).is_err(),
+                "{status} should not fall back to the parent"
+            );
+        }
+    }
+
+    #[test]
+    fn select_reply_root_returns_network_errors() {
+        let (parent, root) = (post_ref("parent"), post_ref("root"));
+        let err = xrpc::Error::<atproto::repo::get_record::Error>::HttpClient(
+            "connection refused".into(),
+        );
+        assert!(select_reply_root(&parent, &root, Err::<(), _>(err.into())).is_err());
+    }
+}